@@ -0,0 +1,173 @@
+//! Watching day 11's seating simulation: ANSI frames for the terminal, PPM
+//! frames and animated GIFs for everything else.
+
+use super::error::{Error, Result};
+use super::problems::day11::{Seat, SeatLayout};
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+/// RGB colours indexed by `Seat as u8`: floor, empty, occupied.
+const PALETTE: [u8; 9] = [40, 40, 40, 60, 160, 60, 200, 50, 50];
+
+fn ansi_colour(seat: Seat) -> &'static str {
+    match seat {
+        Seat::Floor => "\x1b[90m",
+        Seat::Empty => "\x1b[32m",
+        Seat::Occupied => "\x1b[31m",
+    }
+}
+
+pub fn ansi_frame(layout: &SeatLayout) -> String {
+    let mut frame = String::new();
+    for row in layout.grid().rows() {
+        for &seat in row {
+            frame.push_str(ansi_colour(seat));
+            frame.push(seat.into());
+        }
+        frame.push_str("\x1b[0m\n");
+    }
+    frame
+}
+
+/// Redraws the terminal once per generation, `delay` apart.
+pub fn play<W: Write>(frames: &[SeatLayout], delay: Duration, out: &mut W) -> io::Result<()> {
+    for (generation, layout) in frames.iter().enumerate() {
+        write!(out, "\x1b[H\x1b[2J{}", ansi_frame(layout))?;
+        writeln!(
+            out,
+            "generation {}, {} occupied",
+            generation,
+            layout.number_of_occupied_seats()
+        )?;
+        out.flush()?;
+        thread::sleep(delay);
+    }
+    Ok(())
+}
+
+/// One pixel per seat, blown up to `scale` x `scale` squares.
+fn pixels(layout: &SeatLayout, scale: usize) -> Vec<Seat> {
+    let grid = layout.grid();
+    let mut pixels = Vec::with_capacity(grid.width() * grid.height() * scale * scale);
+    for row in grid.rows() {
+        for _ in 0..scale {
+            for &seat in row {
+                pixels.extend(std::iter::repeat_n(seat, scale));
+            }
+        }
+    }
+    pixels
+}
+
+/// A binary (P6) portable pixmap of one generation.
+pub fn write_ppm<W: Write>(layout: &SeatLayout, scale: usize, out: &mut W) -> io::Result<()> {
+    let grid = layout.grid();
+    write!(
+        out,
+        "P6\n{} {}\n255\n",
+        grid.width() * scale,
+        grid.height() * scale
+    )?;
+    let bytes: Vec<u8> = pixels(layout, scale)
+        .into_iter()
+        .flat_map(|seat| {
+            let i = seat as usize * 3;
+            PALETTE[i..i + 3].to_vec()
+        })
+        .collect();
+    out.write_all(&bytes)
+}
+
+/// Writes `genNNNN.ppm` for every frame into `dir`, creating it if needed.
+pub fn export_ppm(frames: &[SeatLayout], scale: usize, dir: &Path) -> Result<()> {
+    let name = dir.display().to_string();
+    fs::create_dir_all(dir).map_err(|e| Error::io(&name, e))?;
+    for (generation, layout) in frames.iter().enumerate() {
+        let path = dir.join(format!("gen{:04}.ppm", generation));
+        let name = path.display().to_string();
+        let mut file =
+            io::BufWriter::new(fs::File::create(&path).map_err(|e| Error::io(&name, e))?);
+        write_ppm(layout, scale, &mut file).map_err(|e| Error::io(&name, e))?;
+    }
+    Ok(())
+}
+
+/// An endlessly looping GIF, `delay` per frame (rounded to the format's 10ms ticks).
+pub fn write_gif<W: Write>(
+    frames: &[SeatLayout],
+    scale: usize,
+    delay: Duration,
+    out: W,
+) -> io::Result<()> {
+    let (width, height) = match frames.first() {
+        Some(layout) => (layout.width() * scale, layout.height() * scale),
+        None => return Ok(()),
+    };
+    let too_big = || io::Error::new(io::ErrorKind::InvalidInput, "frames are too big for a GIF");
+    let width = u16::try_from(width).map_err(|_| too_big())?;
+    let height = u16::try_from(height).map_err(|_| too_big())?;
+    let mut encoder = gif::Encoder::new(out, width, height, &PALETTE).map_err(io::Error::other)?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(io::Error::other)?;
+    for layout in frames {
+        let indices: Vec<u8> = pixels(layout, scale)
+            .into_iter()
+            .map(|seat| seat as u8)
+            .collect();
+        let mut frame = gif::Frame::from_indexed_pixels(width, height, indices, None);
+        frame.delay = (delay.as_millis() / 10).min(u16::MAX as u128) as u16;
+        encoder.write_frame(&frame).map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::problems::day11::Day11;
+    use super::super::problems::Solution;
+    use super::*;
+
+    fn layout() -> SeatLayout {
+        let strings: Vec<String> = ["L.#", "#L."].iter().map(|s| s.to_string()).collect();
+        Day11.parse(&strings).unwrap()
+    }
+
+    #[test]
+    fn ansi_frame_colours_every_seat() {
+        assert_eq!(
+            "\x1b[32mL\x1b[90m.\x1b[31m#\x1b[0m\n\x1b[31m#\x1b[32mL\x1b[90m.\x1b[0m\n",
+            ansi_frame(&layout())
+        );
+    }
+
+    #[test]
+    fn ppm_is_scaled() {
+        let mut out = Vec::new();
+        write_ppm(&layout(), 2, &mut out).unwrap();
+        let header = b"P6\n6 4\n255\n";
+        assert_eq!(&header[..], &out[..header.len()]);
+        assert_eq!(header.len() + 6 * 4 * 3, out.len());
+        // top left pixel is an empty seat
+        assert_eq!(&PALETTE[3..6], &out[header.len()..header.len() + 3]);
+    }
+
+    #[test]
+    fn gif_has_a_frame_per_generation() {
+        let frames = layout().evolution(1);
+        let mut out = Vec::new();
+        write_gif(&frames, 3, Duration::from_millis(200), &mut out).unwrap();
+        assert_eq!(b"GIF89a", &out[..6]);
+        let mut decoder = gif::DecodeOptions::new().read_info(&out[..]).unwrap();
+        let mut count = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!((9, 6, 20), (frame.width, frame.height, frame.delay));
+            count += 1;
+        }
+        assert_eq!(frames.len(), count);
+    }
+}
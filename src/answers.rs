@@ -0,0 +1,170 @@
+use super::error::{Error, Result};
+use super::problems::Answer;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+struct DayAnswers {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    part1: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    part2: Option<String>,
+}
+
+impl DayAnswers {
+    fn part(&self, part: u8) -> Option<&String> {
+        match part {
+            1 => self.part1.as_ref(),
+            2 => self.part2.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn part_mut(&mut self, part: u8) -> Option<&mut Option<String>> {
+        match part {
+            1 => Some(&mut self.part1),
+            2 => Some(&mut self.part2),
+            _ => None,
+        }
+    }
+}
+
+/// Known correct answers, stored as `answers.toml` with one `[dayNN]` table per day.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AnswerStore {
+    days: BTreeMap<String, DayAnswers>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Correct,
+    Wrong { expected: String },
+    Unknown,
+}
+
+impl Verdict {
+    pub fn is_wrong(&self) -> bool {
+        matches!(self, Verdict::Wrong { .. })
+    }
+
+    pub fn status(&self) -> &'static str {
+        match self {
+            Verdict::Correct => "correct",
+            Verdict::Wrong { .. } => "wrong",
+            Verdict::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Correct => write!(f, "✓"),
+            Verdict::Wrong { expected } => write!(f, "✗ (expected {})", expected),
+            Verdict::Unknown => write!(f, "?"),
+        }
+    }
+}
+
+impl AnswerStore {
+    pub fn default_path() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("answers.toml")
+    }
+
+    /// A missing file is an empty store.
+    pub fn load(path: &Path) -> Result<Self> {
+        let name = path.to_string_lossy();
+        match fs::read_to_string(path) {
+            Ok(text) => Self::from_toml(&text).map_err(|e| e.in_file(&name)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(Error::io(&name, e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_toml()).map_err(|e| Error::io(&path.to_string_lossy(), e))
+    }
+
+    fn from_toml(text: &str) -> Result<Self> {
+        let days = toml::from_str(text).map_err(|e| Error::parse(e.to_string()))?;
+        Ok(Self { days })
+    }
+
+    fn to_toml(&self) -> String {
+        toml::to_string(&self.days).expect("answers always serialize")
+    }
+
+    fn key(day: u8) -> String {
+        format!("day{:02}", day)
+    }
+
+    pub fn get(&self, day: u8, part: u8) -> Option<&str> {
+        self.days
+            .get(&Self::key(day))
+            .and_then(|answers| answers.part(part))
+            .map(|s| &s[..])
+    }
+
+    /// Unsolved answers are never recorded.
+    pub fn record(&mut self, day: u8, part: u8, answer: &Answer) {
+        if *answer == Answer::Unsolved {
+            return;
+        }
+        if let Some(slot) = self.days.entry(Self::key(day)).or_default().part_mut(part) {
+            *slot = Some(answer.to_string());
+        }
+    }
+
+    pub fn check(&self, day: u8, part: u8, answer: &Answer) -> Verdict {
+        match self.get(day, part) {
+            Some(expected) if expected == answer.to_string() => Verdict::Correct,
+            Some(expected) => Verdict::Wrong {
+                expected: expected.to_string(),
+            },
+            None => Verdict::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_and_check() {
+        let mut store = AnswerStore::default();
+        store.record(8, 1, &Answer::from(1594));
+        store.record(8, 2, &Answer::Unsolved);
+        assert_eq!(Verdict::Correct, store.check(8, 1, &Answer::from(1594)));
+        assert_eq!(
+            Verdict::Wrong {
+                expected: "1594".to_string()
+            },
+            store.check(8, 1, &Answer::from(5))
+        );
+        assert_eq!(Verdict::Unknown, store.check(8, 2, &Answer::from(758)));
+        assert_eq!(Verdict::Unknown, store.check(9, 1, &Answer::from(1)));
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let mut store = AnswerStore::default();
+        store.record(11, 1, &Answer::from(2476));
+        store.record(1, 2, &Answer::from(157667328));
+        let text = store.to_toml();
+        assert_eq!(
+            "[day01]\npart2 = \"157667328\"\n\n[day11]\npart1 = \"2476\"\n",
+            text
+        );
+        assert_eq!(store, AnswerStore::from_toml(&text).unwrap());
+    }
+
+    #[test]
+    fn malformed_toml() {
+        assert!(AnswerStore::from_toml("[day01\npart1 = 1").is_err());
+    }
+}
@@ -0,0 +1,486 @@
+//! A double-buffered cellular automaton over cells stored in a flat vector.
+//!
+//! The neighbourhood of every cell is resolved once, up front, so the same
+//! engine runs seat layouts, line-of-sight rules and N-dimensional Conway
+//! cubes alike. No puzzle needs the latter yet, so `Shape`, their index
+//! arithmetic, is only built for the tests.
+
+use super::error::{Error, Result};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::hash::Hash;
+use std::thread;
+
+/// The cells around one cell, as handed to the transition rule.
+pub struct Neighbours<'a, C> {
+    cells: &'a [C],
+    indices: std::slice::Iter<'a, u32>,
+}
+
+impl<'a, C> Iterator for Neighbours<'a, C> {
+    type Item = &'a C;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.indices.next().map(|&i| &self.cells[i as usize])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+/// How a simulation ended up once it stopped producing new states.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Outcome {
+    /// The state at `generation` maps onto itself.
+    FixedPoint { generation: usize },
+    /// The state at `start + length` repeats the one at `start`.
+    Cycle { start: usize, length: usize },
+}
+
+/// Anything that advances a generation at a time, so `run` can watch it
+/// for fixed points and cycles.
+pub trait Simulation {
+    /// What `run` remembers of every generation to recognise it again.
+    type State: Eq + Hash;
+
+    /// Advances one generation, returning how many cells changed state.
+    fn step(&mut self) -> usize;
+
+    fn generation(&self) -> usize;
+
+    fn state(&self) -> Self::State;
+
+    /// Steps until the simulation reaches a fixed point or revisits an earlier
+    /// state, giving up with `Error::GenerationLimit` after `max_generations` steps.
+    /// Every state is kept, so matching hashes are confirmed against the cells.
+    fn run(&mut self, max_generations: usize) -> Result<Outcome> {
+        let mut seen = HashMap::new();
+        seen.insert(self.state(), self.generation());
+        for _ in 0..max_generations {
+            if self.step() == 0 {
+                return Ok(Outcome::FixedPoint {
+                    generation: self.generation() - 1,
+                });
+            }
+            if let Some(start) = seen.insert(self.state(), self.generation()) {
+                return Ok(Outcome::Cycle {
+                    start,
+                    length: self.generation() - start,
+                });
+            }
+        }
+        Err(Error::GenerationLimit {
+            limit: max_generations,
+        })
+    }
+}
+
+pub struct Automaton<C, R> {
+    cells: Vec<C>,
+    buffer: Vec<C>,
+    /// Neighbours of cell `i` are `neighbours[starts[i]..starts[i + 1]]`, kept
+    /// as `u32` to halve the footprint of the index on large grids.
+    starts: Vec<u32>,
+    neighbours: Vec<u32>,
+    rule: R,
+    generation: usize,
+    threads: usize,
+}
+
+/// Bands smaller than this are not worth a thread of their own.
+const MIN_CELLS_PER_THREAD: usize = 1 << 16;
+
+impl<C, R> Automaton<C, R>
+where
+    C: Clone + PartialEq + Send + Sync,
+    R: Fn(&C, Neighbours<C>) -> C + Sync,
+{
+    /// `neighbourhood` maps a cell index to the indices of the cells it looks at,
+    /// `rule` computes the next state of a cell from its state and its neighbours.
+    pub fn new<N, I>(cells: Vec<C>, neighbourhood: N, rule: R) -> Self
+    where
+        N: Fn(usize) -> I,
+        I: IntoIterator<Item = usize>,
+    {
+        let mut starts = Vec::with_capacity(cells.len() + 1);
+        let mut neighbours = Vec::new();
+        starts.push(0);
+        for i in 0..cells.len() {
+            neighbours.extend(neighbourhood(i).into_iter().map(|j| j as u32));
+            starts.push(u32::try_from(neighbours.len()).expect("neighbour index exceeds u32"));
+        }
+        Self {
+            buffer: cells.clone(),
+            cells,
+            starts,
+            neighbours,
+            rule,
+            generation: 0,
+            threads: 1,
+        }
+    }
+
+    /// Splits every generation into up to `threads` contiguous bands of cells
+    /// (rows, for a row-major grid) computed in parallel. Without the
+    /// `parallel` feature everything runs on the calling thread.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = if cfg!(feature = "parallel") {
+            threads.max(1)
+        } else {
+            1
+        };
+        self
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    pub fn cells(&self) -> &[C] {
+        &self.cells
+    }
+
+    pub fn into_cells(self) -> Vec<C> {
+        self.cells
+    }
+
+    /// Advances one generation, returning how many cells changed state.
+    pub fn step(&mut self) -> usize {
+        let threads = self
+            .threads
+            .min(self.cells.len() / MIN_CELLS_PER_THREAD)
+            .max(1);
+        let mut next = std::mem::take(&mut self.buffer);
+        let changed = if threads == 1 {
+            self.advance(0, &mut next)
+        } else {
+            let band = self.cells.len().div_ceil(threads);
+            let this = &*self;
+            thread::scope(|scope| {
+                let workers: Vec<_> = next
+                    .chunks_mut(band)
+                    .enumerate()
+                    .map(|(k, next)| scope.spawn(move || this.advance(k * band, next)))
+                    .collect();
+                workers.into_iter().map(|w| w.join().unwrap()).sum()
+            })
+        };
+        self.buffer = std::mem::replace(&mut self.cells, next);
+        self.generation += 1;
+        changed
+    }
+
+    /// Computes the next state of cells `offset..offset + next.len()` into `next`.
+    fn advance(&self, offset: usize, next: &mut [C]) -> usize {
+        let mut changed = 0;
+        for (k, cell) in next.iter_mut().enumerate() {
+            let i = offset + k;
+            let neighbours = Neighbours {
+                cells: &self.cells,
+                indices: self.neighbours[self.starts[i] as usize..self.starts[i + 1] as usize]
+                    .iter(),
+            };
+            *cell = (self.rule)(&self.cells[i], neighbours);
+            if *cell != self.cells[i] {
+                changed += 1;
+            }
+        }
+        changed
+    }
+}
+
+impl<C, R> Simulation for Automaton<C, R>
+where
+    C: Clone + Eq + Send + Sync + Hash,
+    R: Fn(&C, Neighbours<C>) -> C + Sync,
+{
+    type State = Vec<C>;
+
+    fn step(&mut self) -> usize {
+        Automaton::step(self)
+    }
+
+    fn generation(&self) -> usize {
+        self.generation
+    }
+
+    fn state(&self) -> Vec<C> {
+        self.cells.clone()
+    }
+}
+
+/// An N-dimensional box of cells laid out in row-major order, the first
+/// dimension varying fastest.
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shape {
+    dims: Vec<usize>,
+}
+
+#[cfg(test)]
+impl Shape {
+    pub fn new(dims: &[usize]) -> Self {
+        Self {
+            dims: dims.to_vec(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.dims.iter().product()
+    }
+
+    pub fn index_of(&self, coords: &[isize]) -> Option<usize> {
+        let mut index = 0;
+        for (&c, &d) in coords.iter().zip(&self.dims).rev() {
+            if c < 0 || c as usize >= d {
+                return None;
+            }
+            index = index * d + c as usize;
+        }
+        Some(index)
+    }
+
+    pub fn coords_of(&self, mut index: usize) -> Vec<isize> {
+        self.dims
+            .iter()
+            .map(|&d| {
+                let c = index % d;
+                index /= d;
+                c as isize
+            })
+            .collect()
+    }
+
+    /// All `3^n - 1` offsets to the cells touching a cell, diagonals included.
+    pub fn moore_offsets(&self) -> Vec<Vec<isize>> {
+        let mut offsets: Vec<Vec<isize>> = vec![vec![]];
+        for _ in &self.dims {
+            offsets = offsets
+                .into_iter()
+                .flat_map(|o| {
+                    (-1..=1).map(move |d| {
+                        let mut o = o.clone();
+                        o.push(d);
+                        o
+                    })
+                })
+                .collect();
+        }
+        offsets.retain(|o| o.iter().any(|&d| d != 0));
+        offsets
+    }
+
+    /// Indices of the cells touching `index` that lie inside the box.
+    pub fn moore_neighbours<'a>(
+        &'a self,
+        index: usize,
+        offsets: &'a [Vec<isize>],
+    ) -> impl Iterator<Item = usize> + 'a {
+        let coords = self.coords_of(index);
+        offsets.iter().filter_map(move |o| {
+            let p: Vec<isize> = coords.iter().zip(o).map(|(c, d)| c + d).collect();
+            self.index_of(&p)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conway(alive: &bool, neighbours: Neighbours<bool>) -> bool {
+        let n = neighbours.filter(|&&a| a).count();
+        matches!((alive, n), (true, 2) | (true, 3) | (false, 3))
+    }
+
+    type Conway = Automaton<bool, fn(&bool, Neighbours<bool>) -> bool>;
+
+    /// Places the day 17 glider in the middle of a box big enough for six cycles.
+    fn pocket_dimension(dimensions: usize) -> Conway {
+        let mut dims = vec![15, 15];
+        dims.resize(dimensions, 13);
+        let shape = Shape::new(&dims);
+        let mut cells = vec![false; shape.len()];
+        for (y, row) in [".#.", "..#", "###"].iter().enumerate() {
+            for (x, ch) in row.chars().enumerate() {
+                let mut p = vec![x as isize + 6, y as isize + 6];
+                p.resize(dimensions, 6);
+                cells[shape.index_of(&p).unwrap()] = ch == '#';
+            }
+        }
+        let offsets = shape.moore_offsets();
+        Automaton::new(
+            cells,
+            |i| shape.moore_neighbours(i, &offsets).collect::<Vec<_>>(),
+            conway,
+        )
+    }
+
+    #[test]
+    fn shape_indexing() {
+        let shape = Shape::new(&[3, 4, 5]);
+        assert_eq!(60, shape.len());
+        assert_eq!(Some(1 + 2 * 3 + 3 * 12), shape.index_of(&[1, 2, 3]));
+        assert_eq!(vec![1, 2, 3], shape.coords_of(1 + 2 * 3 + 3 * 12));
+        assert_eq!(None, shape.index_of(&[3, 0, 0]));
+        assert_eq!(None, shape.index_of(&[0, -1, 0]));
+        assert_eq!(26, shape.moore_offsets().len());
+        assert_eq!(7, shape.moore_neighbours(0, &shape.moore_offsets()).count());
+    }
+
+    #[test]
+    fn blinker_changes_four_cells_per_generation() {
+        let shape = Shape::new(&[5, 5]);
+        let offsets = shape.moore_offsets();
+        let mut cells = vec![false; 25];
+        for x in 1..4 {
+            cells[shape.index_of(&[x, 2]).unwrap()] = true;
+        }
+        let initial = cells.clone();
+        let mut automaton = Automaton::new(
+            cells,
+            |i| shape.moore_neighbours(i, &offsets).collect::<Vec<_>>(),
+            conway,
+        );
+        assert_eq!(4, automaton.step());
+        assert!(automaton.cells()[shape.index_of(&[2, 1]).unwrap()]);
+        assert_eq!(4, automaton.step());
+        assert_eq!(&initial[..], automaton.cells());
+        assert_eq!(2, automaton.generation());
+    }
+
+    #[test]
+    fn blinker_is_a_cycle_of_two() {
+        let shape = Shape::new(&[5, 5]);
+        let offsets = shape.moore_offsets();
+        let mut cells = vec![false; 25];
+        for x in 1..4 {
+            cells[shape.index_of(&[x, 2]).unwrap()] = true;
+        }
+        let mut automaton = Automaton::new(
+            cells,
+            |i| shape.moore_neighbours(i, &offsets).collect::<Vec<_>>(),
+            conway,
+        );
+        assert_eq!(
+            Outcome::Cycle {
+                start: 0,
+                length: 2
+            },
+            automaton.run(10).unwrap()
+        );
+    }
+
+    #[test]
+    fn generation_limit() {
+        // A counter never repeats within the limit.
+        let mut automaton = Automaton::new(vec![0u32], |_| None, |&n, _| n + 1);
+        match automaton.run(100) {
+            Err(Error::GenerationLimit { limit }) => assert_eq!(100, limit),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(100, automaton.generation());
+    }
+
+    #[test]
+    fn cycle_after_a_transient() {
+        // 0 -> 1 -> 2 -> 3 -> 4 -> 2 ...
+        let mut automaton =
+            Automaton::new(vec![0u32], |_| None, |&n, _| if n == 4 { 2 } else { n + 1 });
+        assert_eq!(
+            Outcome::Cycle {
+                start: 2,
+                length: 3
+            },
+            automaton.run(100).unwrap()
+        );
+    }
+
+    /// Counts up, with every state hashing alike.
+    struct Colliding(u32);
+
+    #[derive(PartialEq, Eq)]
+    struct Count(u32);
+
+    impl Hash for Count {
+        fn hash<H: std::hash::Hasher>(&self, _: &mut H) {}
+    }
+
+    impl Simulation for Colliding {
+        type State = Count;
+
+        fn step(&mut self) -> usize {
+            self.0 += 1;
+            1
+        }
+
+        fn generation(&self) -> usize {
+            self.0 as usize
+        }
+
+        fn state(&self) -> Count {
+            Count(self.0)
+        }
+    }
+
+    #[test]
+    fn hash_collisions_are_not_cycles() {
+        match Colliding(0).run(20) {
+            Err(Error::GenerationLimit { limit }) => assert_eq!(20, limit),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parallel_bands_match_serial() {
+        let shape = Shape::new(&[512, 512]);
+        let offsets = shape.moore_offsets();
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let cells: Vec<bool> = (0..shape.len())
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                seed.is_multiple_of(3)
+            })
+            .collect();
+        let neighbourhood = |i| shape.moore_neighbours(i, &offsets).collect::<Vec<_>>();
+        let mut serial = Automaton::new(cells.clone(), neighbourhood, conway);
+        let mut parallel = Automaton::new(cells, neighbourhood, conway).with_threads(3);
+        for _ in 0..5 {
+            assert_eq!(serial.step(), parallel.step());
+            assert_eq!(serial.cells(), parallel.cells());
+        }
+    }
+
+    #[test]
+    fn still_life_is_stable_immediately() {
+        let shape = Shape::new(&[4, 4]);
+        let offsets = shape.moore_offsets();
+        let mut cells = vec![false; 16];
+        for p in &[[1, 1], [1, 2], [2, 1], [2, 2]] {
+            cells[shape.index_of(p).unwrap()] = true;
+        }
+        let mut automaton = Automaton::new(
+            cells,
+            |i| shape.moore_neighbours(i, &offsets).collect::<Vec<_>>(),
+            conway,
+        );
+        assert_eq!(
+            Outcome::FixedPoint { generation: 0 },
+            automaton.run(10).unwrap()
+        );
+    }
+
+    #[test]
+    fn conway_cubes_in_three_and_four_dimensions() {
+        for &(dimensions, expected) in &[(3, 112), (4, 848)] {
+            let mut automaton = pocket_dimension(dimensions);
+            for _ in 0..6 {
+                automaton.step();
+            }
+            assert_eq!(expected, automaton.cells().iter().filter(|&&a| a).count());
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use super::automaton::{Automaton, Neighbours};
+use super::error::{Error, Result};
+use super::problems::day11::{Seat, SeatLayout};
+use super::problems::{Day, Timings};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub const PHASES: [&str; 3] = ["parse", "part1", "part2"];
+
+/// Summary of the samples of one phase, in nanoseconds so baselines stay plain JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub min_ns: u64,
+    pub median_ns: u64,
+    pub p95_ns: u64,
+}
+
+impl Stats {
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        let mut ns: Vec<u64> = samples.iter().map(|d| d.as_nanos() as u64).collect();
+        ns.sort_unstable();
+        let n = ns.len();
+        let median_ns = if n.is_multiple_of(2) {
+            (ns[n / 2 - 1] + ns[n / 2]) / 2
+        } else {
+            ns[n / 2]
+        };
+        let p95_index = ((n as f64) * 0.95).ceil() as usize;
+        Self {
+            min_ns: ns[0],
+            median_ns,
+            p95_ns: ns[p95_index.max(1) - 1],
+        }
+    }
+}
+
+/// Phase name to stats for one day
+pub type DayStats = BTreeMap<String, Stats>;
+
+/// Day key (`day08`) to per-phase stats, the format of a saved baseline.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub days: BTreeMap<String, DayStats>,
+}
+
+impl Baseline {
+    pub fn key(day: u8) -> String {
+        format!("day{:02}", day)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let name = path.to_string_lossy();
+        let text = fs::read_to_string(path).map_err(|e| Error::io(&name, e))?;
+        serde_json::from_str(&text).map_err(|e| Error::parse(e.to_string()).in_file(&name))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = serde_json::to_string_pretty(self).expect("baselines always serialize");
+        fs::write(path, text).map_err(|e| Error::io(&path.to_string_lossy(), e))
+    }
+
+    pub fn get(&self, day: u8, phase: &str) -> Option<&Stats> {
+        self.days.get(&Self::key(day)).and_then(|d| d.get(phase))
+    }
+}
+
+/// Runs every phase of `day` `iterations` times.
+pub fn bench_day(day: &Day, strings: &[String], iterations: usize) -> Result<DayStats> {
+    let samples: Vec<Timings> = (0..iterations.max(1))
+        .map(|_| day.solution.time(strings))
+        .collect::<Result<Vec<Timings>>>()?;
+    let phase = |f: fn(&Timings) -> Duration| {
+        Stats::from_samples(&samples.iter().map(f).collect::<Vec<Duration>>())
+    };
+    let mut stats = DayStats::new();
+    stats.insert(PHASES[0].to_string(), phase(|t| t.parse));
+    stats.insert(PHASES[1].to_string(), phase(|t| t.part1));
+    stats.insert(PHASES[2].to_string(), phase(|t| t.part2));
+    Ok(stats)
+}
+
+/// Serial against banded parallel stepping of the same synthetic seat layout
+/// under part 2's line-of-sight rule, the one that runs on the automaton.
+pub struct SeatsParity {
+    pub threads: usize,
+    pub serial: Duration,
+    pub parallel: Duration,
+    pub identical: bool,
+}
+
+/// Steps `simulation` `generations` times, timing only the steps.
+fn timed_steps<R>(mut simulation: Automaton<Seat, R>, generations: usize) -> (Vec<Seat>, Duration)
+where
+    R: Fn(&Seat, Neighbours<Seat>) -> Seat + Sync,
+{
+    let start = Instant::now();
+    for _ in 0..generations {
+        simulation.step();
+    }
+    let elapsed = start.elapsed();
+    (simulation.into_cells(), elapsed)
+}
+
+/// The neighbour index is built before the clock starts, one simulation at a
+/// time to keep only one index in memory.
+pub fn seats_parity(size: usize, generations: usize, threads: usize) -> SeatsParity {
+    let layout = SeatLayout::synthetic(size, size, 0x2020_1211);
+    let (serial, serial_time) = timed_steps(layout.visibility_simulation(1), generations);
+    let parallel = layout.visibility_simulation(threads);
+    let threads = parallel.threads();
+    let (parallel, parallel_time) = timed_steps(parallel, generations);
+    SeatsParity {
+        threads,
+        serial: serial_time,
+        parallel: parallel_time,
+        identical: serial == parallel,
+    }
+}
+
+pub struct Nanos(pub u64);
+
+impl fmt::Display for Nanos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ns = self.0 as f64;
+        if ns >= 1e9 {
+            write!(f, "{:.2}s", ns / 1e9)
+        } else if ns >= 1e6 {
+            write!(f, "{:.2}ms", ns / 1e6)
+        } else if ns >= 1e3 {
+            write!(f, "{:.2}µs", ns / 1e3)
+        } else {
+            write!(f, "{}ns", self.0)
+        }
+    }
+}
+
+/// Relative change of the median against a baseline, e.g. `-12.5%`.
+pub fn change(current: &Stats, baseline: &Stats) -> String {
+    if baseline.median_ns == 0 {
+        return "n/a".to_string();
+    }
+    let ratio = current.median_ns as f64 / baseline.median_ns as f64;
+    format!("{:+.1}%", (ratio - 1.0) * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_of_samples() {
+        let samples: Vec<Duration> = (1..=20).rev().map(Duration::from_nanos).collect();
+        let stats = Stats::from_samples(&samples);
+        assert_eq!(1, stats.min_ns);
+        assert_eq!(10, stats.median_ns);
+        assert_eq!(19, stats.p95_ns);
+        let single = Stats::from_samples(&[Duration::from_nanos(7)]);
+        assert_eq!((7, 7, 7), (single.min_ns, single.median_ns, single.p95_ns));
+    }
+
+    #[test]
+    fn change_against_baseline() {
+        let stats = |median_ns| Stats {
+            min_ns: 0,
+            median_ns,
+            p95_ns: 0,
+        };
+        assert_eq!("-50.0%", change(&stats(50), &stats(100)));
+        assert_eq!("+25.0%", change(&stats(125), &stats(100)));
+    }
+
+    #[test]
+    fn nanos_display() {
+        assert_eq!("999ns", Nanos(999).to_string());
+        assert_eq!("1.50µs", Nanos(1_500).to_string());
+        assert_eq!("2.00ms", Nanos(2_000_000).to_string());
+    }
+
+    #[test]
+    fn seats_parity_on_a_small_layout() {
+        let parity = seats_parity(400, 3, 4);
+        assert!(parity.identical);
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io {
+        path: String,
+        source: io::Error,
+    },
+    Parse {
+        file: Option<String>,
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+    },
+    #[cfg_attr(not(feature = "fetch"), allow(dead_code))]
+    Fetch {
+        day: u8,
+        message: String,
+    },
+    /// A simulation neither settled nor started repeating itself in time.
+    GenerationLimit {
+        limit: usize,
+    },
+}
+
+impl Error {
+    pub fn io(path: &str, source: io::Error) -> Self {
+        Error::Io {
+            path: path.to_string(),
+            source,
+        }
+    }
+
+    #[cfg_attr(not(feature = "fetch"), allow(dead_code))]
+    pub fn fetch<S: Into<String>>(day: u8, message: S) -> Self {
+        Error::Fetch {
+            day,
+            message: message.into(),
+        }
+    }
+
+    /// A parse error without a location yet, callers higher up attach line and file.
+    pub fn parse<S: Into<String>>(message: S) -> Self {
+        Error::Parse {
+            file: None,
+            line: None,
+            column: None,
+            message: message.into(),
+        }
+    }
+
+    /// 1-based line number within the input
+    pub fn at_line(mut self, n: usize) -> Self {
+        if let Error::Parse { line, .. } = &mut self {
+            line.get_or_insert(n);
+        }
+        self
+    }
+
+    /// 1-based column within the line
+    pub fn at_column(mut self, n: usize) -> Self {
+        if let Error::Parse { column, .. } = &mut self {
+            column.get_or_insert(n);
+        }
+        self
+    }
+
+    pub fn in_file(mut self, path: &str) -> Self {
+        if let Error::Parse { file, .. } = &mut self {
+            file.get_or_insert_with(|| path.to_string());
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path, source),
+            Error::Parse {
+                file,
+                line,
+                column,
+                message,
+            } => {
+                match (file, line) {
+                    (Some(file), Some(line)) => write!(f, "{}:{}", file, line)?,
+                    (Some(file), None) => write!(f, "{}", file)?,
+                    (None, Some(line)) => write!(f, "line {}", line)?,
+                    (None, None) => return write!(f, "{}", message),
+                }
+                if let (Some(_), Some(column)) = (line, column) {
+                    write!(f, ":{}", column)?;
+                }
+                write!(f, ": {}", message)
+            }
+            Error::Fetch { day, message } => write!(f, "fetching day {}: {}", day, message),
+            Error::GenerationLimit { limit } => {
+                write!(f, "no fixed point or cycle within {} generations", limit)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Parse { .. } | Error::Fetch { .. } | Error::GenerationLimit { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_with_location() {
+        let e = Error::parse("invalid argument \"+x\"")
+            .at_line(17)
+            .in_file("inputs/day08_1");
+        assert_eq!("inputs/day08_1:17: invalid argument \"+x\"", e.to_string());
+        let e = Error::parse("unexpected 'X'").at_column(4).at_line(2);
+        assert_eq!("line 2:4: unexpected 'X'", e.to_string());
+    }
+
+    #[test]
+    fn innermost_location_wins() {
+        let e = Error::parse("oops").at_line(3).at_line(10);
+        assert_eq!("line 3: oops", e.to_string());
+    }
+}
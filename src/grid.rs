@@ -0,0 +1,317 @@
+//! A rectangular grid of cells shared by the map-based puzzles.
+
+use super::error::{Error, Result};
+use std::fmt;
+use std::ops::{Add, Index, IndexMut, Mul};
+
+/// A position or an offset on a grid, `x` grows to the right and `y` downwards.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Point {
+    pub x: isize,
+    pub y: isize,
+}
+
+impl Point {
+    pub const fn new(x: isize, y: isize) -> Self {
+        Self { x, y }
+    }
+}
+
+impl Add for Point {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl Mul<isize> for Point {
+    type Output = Self;
+
+    fn mul(self, k: isize) -> Self {
+        Self::new(self.x * k, self.y * k)
+    }
+}
+
+/// The eight surrounding cells, diagonals included, row by row
+pub const NEIGHBOURS_8: [Point; 8] = [
+    Point::new(-1, -1),
+    Point::new(0, -1),
+    Point::new(1, -1),
+    Point::new(-1, 0),
+    Point::new(1, 0),
+    Point::new(-1, 1),
+    Point::new(0, 1),
+    Point::new(1, 1),
+];
+
+/// A rectangular grid stored row by row.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// Builds a grid from a character map, `cell` returns `None` for characters it does not know.
+    pub fn parse<F>(strings: &[String], cell: F) -> Result<Self>
+    where
+        F: Fn(char) -> Option<T>,
+    {
+        let width = strings.first().map_or(0, |s| s.chars().count());
+        if width == 0 {
+            return Err(Error::parse("the map is empty"));
+        }
+        let mut cells = Vec::with_capacity(width * strings.len());
+        for (y, line) in strings.iter().enumerate() {
+            let mut n = 0;
+            for (x, ch) in line.chars().enumerate() {
+                let value = cell(ch).ok_or_else(|| {
+                    Error::parse(format!("unexpected {:?}", ch))
+                        .at_column(x + 1)
+                        .at_line(y + 1)
+                })?;
+                cells.push(value);
+                n += 1;
+            }
+            if n != width {
+                return Err(
+                    Error::parse(format!("expected {} cells, got {}", width, n)).at_line(y + 1)
+                );
+            }
+        }
+        Ok(Self {
+            width,
+            height: strings.len(),
+            cells,
+        })
+    }
+
+    /// Wraps cells laid out row by row, `cells.len()` must be `width * height`.
+    pub fn from_cells(width: usize, height: usize, cells: Vec<T>) -> Self {
+        assert_eq!(
+            width * height,
+            cells.len(),
+            "cells do not fill a {}x{} grid",
+            width,
+            height
+        );
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        p.x >= 0 && p.y >= 0 && (p.x as usize) < self.width && (p.y as usize) < self.height
+    }
+
+    /// Position of `p` in `cells()`
+    pub fn index_of(&self, p: Point) -> Option<usize> {
+        if self.contains(p) {
+            Some(p.y as usize * self.width + p.x as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn point_of(&self, index: usize) -> Point {
+        Point::new((index % self.width) as isize, (index / self.width) as isize)
+    }
+
+    pub fn get(&self, p: Point) -> Option<&T> {
+        self.index_of(p).map(|i| &self.cells[i])
+    }
+
+    /// Treats the grid as repeating endlessly in both directions.
+    pub fn get_wrapping(&self, p: Point) -> &T {
+        let x = p.x.rem_euclid(self.width as isize);
+        let y = p.y.rem_euclid(self.height as isize);
+        &self[Point::new(x, y)]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.cells.chunks(self.width)
+    }
+
+    #[cfg(test)]
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        self.cells.iter().skip(x).step_by(self.width)
+    }
+
+    #[cfg(test)]
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+        (0..self.width).map(move |x| self.column(x))
+    }
+
+    /// Cells at the given offsets from `p` that are inside the grid.
+    pub fn neighbours<'a>(
+        &'a self,
+        p: Point,
+        offsets: &'a [Point],
+    ) -> impl Iterator<Item = (Point, &'a T)> + 'a {
+        offsets
+            .iter()
+            .map(move |&d| p + d)
+            .filter_map(move |q| self.get(q).map(|v| (q, v)))
+    }
+
+    pub fn neighbours8(&self, p: Point) -> impl Iterator<Item = (Point, &T)> {
+        self.neighbours(p, &NEIGHBOURS_8)
+    }
+
+    /// Cells from `p + direction` onwards in `direction`, up to the edge of the grid.
+    pub fn ray(&self, p: Point, direction: Point) -> impl Iterator<Item = (Point, &T)> {
+        (1..)
+            .map(move |k| p + direction * k)
+            .map(move |q| self.get(q).map(|v| (q, v)))
+            .take_while(Option::is_some)
+            .flatten()
+    }
+}
+
+impl<T> Index<Point> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, p: Point) -> &T {
+        match self.index_of(p) {
+            Some(i) => &self.cells[i],
+            None => panic!(
+                "{:?} is outside of a {}x{} grid",
+                p, self.width, self.height
+            ),
+        }
+    }
+}
+
+impl<T> IndexMut<Point> for Grid<T> {
+    fn index_mut(&mut self, p: Point) -> &mut T {
+        match self.index_of(p) {
+            Some(i) => &mut self.cells[i],
+            None => panic!(
+                "{:?} is outside of a {}x{} grid",
+                p, self.width, self.height
+            ),
+        }
+    }
+}
+
+/// Renders the grid as the character map it was parsed from.
+impl<T: Copy + Into<char>> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.rows() {
+            let line: String = row.iter().map(|&c| c.into()).collect();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    struct Bit(bool);
+
+    impl From<Bit> for char {
+        fn from(b: Bit) -> char {
+            if b.0 {
+                '#'
+            } else {
+                '.'
+            }
+        }
+    }
+
+    fn bit(c: char) -> Option<Bit> {
+        match c {
+            '#' => Some(Bit(true)),
+            '.' => Some(Bit(false)),
+            _ => None,
+        }
+    }
+
+    fn grid(rows: &[&str]) -> Grid<Bit> {
+        let strings: Vec<String> = rows.iter().map(|s| s.to_string()).collect();
+        Grid::parse(&strings, bit).unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        let g = grid(&["#..", ".#.", "..#", "##."]);
+        assert_eq!((3, 4), (g.width(), g.height()));
+        assert_eq!("#..\n.#.\n..#\n##.\n", g.to_string());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let strings: Vec<String> = vec!["#.".to_string(), "#x".to_string()];
+        assert_eq!(
+            "line 2:2: unexpected 'x'",
+            Grid::parse(&strings, bit).unwrap_err().to_string()
+        );
+        let strings: Vec<String> = vec!["#.".to_string(), "#".to_string()];
+        assert_eq!(
+            "line 2: expected 2 cells, got 1",
+            Grid::parse(&strings, bit).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn bounds_checked_and_wrapping_access() {
+        let g = grid(&["#..", ".#."]);
+        assert_eq!(Some(&Bit(true)), g.get(Point::new(1, 1)));
+        assert_eq!(None, g.get(Point::new(3, 0)));
+        assert_eq!(None, g.get(Point::new(0, -1)));
+        assert_eq!(&Bit(true), g.get_wrapping(Point::new(4, 3)));
+        assert_eq!(&Bit(true), g.get_wrapping(Point::new(-3, -2)));
+    }
+
+    #[test]
+    fn neighbourhoods_are_clipped() {
+        let g = grid(&["#..", ".#.", "..#"]);
+        assert_eq!(3, g.neighbours8(Point::new(0, 0)).count());
+        assert_eq!(8, g.neighbours8(Point::new(1, 1)).count());
+        let occupied = g.neighbours8(Point::new(1, 1)).filter(|(_, b)| b.0).count();
+        assert_eq!(2, occupied);
+    }
+
+    #[test]
+    fn rays() {
+        let g = grid(&["#..", ".#.", "..#"]);
+        let diagonal: Vec<Point> = g
+            .ray(Point::new(0, 0), Point::new(1, 1))
+            .map(|(p, _)| p)
+            .collect();
+        assert_eq!(vec![Point::new(1, 1), Point::new(2, 2)], diagonal);
+        assert_eq!(0, g.ray(Point::new(0, 0), Point::new(-1, 0)).count());
+    }
+
+    #[test]
+    fn rows_and_columns() {
+        let g = grid(&["#..", ".##"]);
+        let rows: Vec<usize> = g.rows().map(|r| r.iter().filter(|b| b.0).count()).collect();
+        assert_eq!(vec![1, 2], rows);
+        let columns: Vec<usize> = g.columns().map(|c| c.filter(|b| b.0).count()).collect();
+        assert_eq!(vec![1, 1, 1], columns);
+        assert_eq!(
+            Point::new(2, 1),
+            g.point_of(g.index_of(Point::new(2, 1)).unwrap())
+        );
+    }
+}
@@ -0,0 +1,319 @@
+use super::error::{Error, Result};
+use super::utils::read_strings_from_file;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+
+/// Where the puzzle input for a run comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Stdin,
+    File(PathBuf),
+    Day(u8),
+}
+
+impl Source {
+    /// `-` stands for stdin, anything else is a path
+    pub fn from_arg(arg: &Path) -> Self {
+        if arg == Path::new("-") {
+            Source::Stdin
+        } else {
+            Source::File(arg.to_path_buf())
+        }
+    }
+}
+
+/// Lines of a puzzle input together with a name to report errors against.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawInput {
+    pub name: String,
+    pub lines: Vec<String>,
+}
+
+impl RawInput {
+    /// FNV-1a hash of the lines, to tell which input an answer was computed from.
+    pub fn checksum(&self) -> String {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for line in self.lines.iter() {
+            for byte in line.bytes().chain(std::iter::once(b'\n')) {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        format!("{:016x}", hash)
+    }
+
+    fn from_text(name: String, text: &str) -> Self {
+        Self {
+            name,
+            lines: text.lines().map(String::from).collect(),
+        }
+    }
+}
+
+/// Downloads the input of a day when it is neither on disk nor in the cache.
+pub trait Fetcher {
+    fn fetch(&self, day: u8) -> Result<String>;
+}
+
+pub struct InputProvider {
+    dir: PathBuf,
+    cache_dir: Option<PathBuf>,
+    fetcher: Option<Box<dyn Fetcher>>,
+}
+
+impl InputProvider {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            cache_dir: None,
+            fetcher: None,
+        }
+    }
+
+    /// Inputs next to the sources, so runs work from any working directory.
+    pub fn default_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("inputs")
+    }
+
+    pub fn with_cache<P: Into<PathBuf>>(mut self, cache_dir: P) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    #[cfg_attr(not(feature = "fetch"), allow(dead_code))]
+    pub fn with_fetcher<F: Fetcher + 'static>(mut self, fetcher: F) -> Self {
+        self.fetcher = Some(Box::new(fetcher));
+        self
+    }
+
+    pub fn load(&self, source: &Source) -> Result<RawInput> {
+        match source {
+            Source::Stdin => {
+                let lines = io::stdin()
+                    .lock()
+                    .lines()
+                    .collect::<io::Result<Vec<String>>>()
+                    .map_err(|e| Error::io("<stdin>", e))?;
+                Ok(RawInput {
+                    name: "<stdin>".to_string(),
+                    lines,
+                })
+            }
+            Source::File(path) => Self::load_file(path),
+            Source::Day(day) => self.load_day(*day),
+        }
+    }
+
+    fn load_file(path: &Path) -> Result<RawInput> {
+        let name = path.to_string_lossy().to_string();
+        let lines = read_strings_from_file(&name)?;
+        Ok(RawInput { name, lines })
+    }
+
+    /// Looks in the inputs directory, then the embedded inputs, then the cache,
+    /// and finally fetches the input and stores it in the cache.
+    fn load_day(&self, day: u8) -> Result<RawInput> {
+        let file_name = format!("day{:02}_1", day);
+        let path = self.dir.join(&file_name);
+        if path.is_file() {
+            return Self::load_file(&path);
+        }
+        if let Some(text) = embedded(day) {
+            return Ok(RawInput::from_text(
+                format!("<embedded {}>", file_name),
+                text,
+            ));
+        }
+        let cached = self.cache_dir.as_ref().map(|dir| dir.join(&file_name));
+        if let Some(cached) = cached.as_ref().filter(|p| p.is_file()) {
+            return Self::load_file(cached);
+        }
+        match (&self.fetcher, cached) {
+            (Some(fetcher), Some(cached)) => {
+                let text = fetcher.fetch(day)?;
+                let cached_name = cached.to_string_lossy().to_string();
+                if let Some(dir) = cached.parent() {
+                    fs::create_dir_all(dir).map_err(|e| Error::io(&cached_name, e))?;
+                }
+                fs::write(&cached, &text).map_err(|e| Error::io(&cached_name, e))?;
+                Ok(RawInput::from_text(cached_name, &text))
+            }
+            (Some(fetcher), None) => {
+                let text = fetcher.fetch(day)?;
+                Ok(RawInput::from_text(
+                    format!("<fetched {}>", file_name),
+                    &text,
+                ))
+            }
+            (None, _) => Self::load_file(&path),
+        }
+    }
+}
+
+macro_rules! embedded_inputs {
+    ($($day:literal => $file:literal),* $(,)?) => {
+        #[cfg(feature = "embedded-inputs")]
+        fn embedded(day: u8) -> Option<&'static str> {
+            match day {
+                $($day => Some(include_str!(concat!("../inputs/", $file))),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+embedded_inputs! {
+    1 => "day01_1",
+    2 => "day02_1",
+    3 => "day03_1",
+    4 => "day04_1",
+    5 => "day05_1",
+    6 => "day06_1",
+    7 => "day07_1",
+    8 => "day08_1",
+    9 => "day09_1",
+    10 => "day10_1",
+    11 => "day11_1",
+}
+
+#[cfg(not(feature = "embedded-inputs"))]
+fn embedded(_day: u8) -> Option<&'static str> {
+    None
+}
+
+/// Fetches inputs over HTTP with an adventofcode.com session cookie.
+#[cfg(feature = "fetch")]
+pub struct HttpFetcher {
+    base_url: String,
+    session: String,
+}
+
+#[cfg(feature = "fetch")]
+impl HttpFetcher {
+    pub const BASE_URL: &'static str = "https://adventofcode.com";
+
+    pub fn new(base_url: &str, session: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            session: session.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "fetch")]
+impl Fetcher for HttpFetcher {
+    fn fetch(&self, day: u8) -> Result<String> {
+        let url = format!("{}/2020/day/{}/input", self.base_url, day);
+        ureq::get(&url)
+            .set("Cookie", &format!("session={}", self.session))
+            .call()
+            .map_err(|e| Error::fetch(day, e.to_string()))?
+            .into_string()
+            .map_err(|e| Error::fetch(day, e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aoc2020-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    struct CountingFetcher {
+        calls: Rc<Cell<usize>>,
+    }
+
+    impl Fetcher for CountingFetcher {
+        fn fetch(&self, day: u8) -> Result<String> {
+            self.calls.set(self.calls.get() + 1);
+            Ok(format!("fetched {}\nsecond line\n", day))
+        }
+    }
+
+    #[test]
+    fn reads_day_from_inputs_dir() {
+        let dir = temp_dir("inputs");
+        fs::write(dir.join("day03_1"), "..#\n#..\n").unwrap();
+        let input = InputProvider::new(&dir).load(&Source::Day(3)).unwrap();
+        assert_eq!(vec!["..#", "#.."], input.lines);
+        assert!(input.name.ends_with("day03_1"));
+    }
+
+    #[test]
+    fn checksum_ignores_line_endings() {
+        let unix = RawInput::from_text("a".to_string(), "1\n2\n");
+        let dos = RawInput::from_text("b".to_string(), "1\r\n2");
+        assert_eq!(unix.checksum(), dos.checksum());
+        assert_eq!(
+            "cbf29ce484222325",
+            RawInput::from_text("c".to_string(), "").checksum()
+        );
+        assert_ne!(
+            unix.checksum(),
+            RawInput::from_text("d".to_string(), "12").checksum()
+        );
+    }
+
+    #[test]
+    fn missing_day_without_fetcher_is_an_io_error() {
+        let dir = temp_dir("missing");
+        let e = InputProvider::new(&dir).load(&Source::Day(25)).unwrap_err();
+        assert!(matches!(e, Error::Io { .. }));
+    }
+
+    #[test]
+    fn fetches_once_into_cache() {
+        let dir = temp_dir("cache");
+        let calls = Rc::new(Cell::new(0));
+        let provider = InputProvider::new(dir.join("inputs"))
+            .with_cache(dir.join("cache"))
+            .with_fetcher(CountingFetcher {
+                calls: calls.clone(),
+            });
+        let first = provider.load(&Source::Day(24)).unwrap();
+        let second = provider.load(&Source::Day(24)).unwrap();
+        assert_eq!(vec!["fetched 24", "second line"], first.lines);
+        assert_eq!(first, second);
+        assert_eq!(1, calls.get());
+        assert!(dir.join("cache").join("day24_1").is_file());
+    }
+
+    #[cfg(feature = "fetch")]
+    #[test]
+    fn http_fetcher_against_local_server() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            let n = stream.read(&mut buf).unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]).to_string();
+            let body = "1721\n979\n";
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            request
+        });
+        let fetcher = HttpFetcher::new(&format!("http://{}/", addr), "secret");
+        assert_eq!("1721\n979\n", fetcher.fetch(1).unwrap());
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /2020/day/1/input "));
+        assert!(request.contains("session=secret"));
+    }
+}
@@ -1,9 +1,596 @@
+mod animate;
+mod answers;
+mod automaton;
+mod bench;
+mod error;
+mod grid;
 // mod graph;
+mod input;
 mod problems;
+mod report;
 mod utils;
+mod vm;
 
-use problems::*;
+use answers::{AnswerStore, Verdict};
+use bench::{Baseline, Nanos};
+use clap::{Parser, Subcommand};
+use input::{InputProvider, RawInput, Source};
+use log::LevelFilter;
+use problems::day08::{self, Day08, Debugger, Operator};
+use problems::day11::Day11;
+use problems::{Answer, Day, Solution};
+use report::{Format, Record, Reporter};
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Parser)]
+#[command(
+    name = "adventofcode2020",
+    about = "https://adventofcode.com/2020 in Rust"
+)]
+struct Cli {
+    /// More detail on stderr: -v for debug output, -vv for traces
+    #[arg(short, long, global = true, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+    /// Only report errors
+    #[arg(short, long, global = true)]
+    quiet: bool,
+    /// Directory holding the dayNN_1 input files
+    #[arg(long, global = true, env = "AOC_INPUTS")]
+    inputs: Option<PathBuf>,
+    /// Directory where downloaded inputs are cached
+    #[arg(long, global = true, env = "AOC_CACHE")]
+    cache: Option<PathBuf>,
+    /// adventofcode.com session cookie, enables downloading missing inputs
+    #[arg(long, global = true, env = "AOC_SESSION", hide_env_values = true)]
+    session: Option<String>,
+    /// File with the known correct answers
+    #[arg(long, global = true, env = "AOC_ANSWERS")]
+    answers: Option<PathBuf>,
+    /// How answers are printed
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run one or all of the registered days
+    Run {
+        /// Day to run, all days when omitted
+        #[arg(long)]
+        day: Option<u8>,
+        /// Part to run, both parts when omitted
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=2))]
+        part: Option<u8>,
+        /// Input file or `-` for stdin, defaults to the day's file in the inputs directory
+        #[arg(long, requires = "day")]
+        input: Option<PathBuf>,
+        /// Store the answers as known correct, only for the puzzle inputs
+        #[arg(long, conflicts_with = "input")]
+        record: bool,
+    },
+    /// Run every registered day and check the answers against the recorded ones
+    Verify,
+    /// Step through day 8's program in an interactive debugger
+    Debug {
+        /// Input file or `-` for stdin, defaults to day 8's file in the inputs directory
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Read debugger commands from this file instead of the terminal
+        #[arg(long)]
+        commands: Option<PathBuf>,
+    },
+    /// Print day 8's program with labels, basic blocks and unreachable code marked
+    Disasm {
+        /// Input file or `-` for stdin, defaults to day 8's file in the inputs directory
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Show, without running day 8's program, how a run from every instruction
+    /// ends, what flipping it does and which accumulator values reach it
+    Analyze {
+        /// Input file or `-` for stdin, defaults to day 8's file in the inputs directory
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Record every instruction day 8's program executes, as text or with --format json
+    Trace {
+        /// Input file or `-` for stdin, defaults to day 8's file in the inputs directory
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Search for the fewest edits that make day 8's program terminate
+    Repair {
+        /// Input file or `-` for stdin, defaults to day 8's file in the inputs directory
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Edits to try, comma separated: flip, swap, adjust=K (K up to 1000), delete or insert=INSTRUCTION
+        #[arg(long, value_delimiter = ',', default_value = "flip")]
+        edits: Vec<Operator>,
+        /// Most edits combined in one repair
+        #[arg(long, default_value_t = 1)]
+        max_edits: usize,
+    },
+    /// Play day 11's seating simulation in the terminal, or export its frames
+    Animate {
+        /// Which part's seating rule to play
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=2))]
+        part: u8,
+        /// Input file or `-` for stdin, defaults to day 11's file in the inputs directory
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Milliseconds between frames
+        #[arg(long, default_value_t = 100)]
+        delay: u64,
+        /// Write frames instead of playing them: an animated GIF for a `.gif` path,
+        /// otherwise a directory of genNNNN.ppm files
+        #[arg(long)]
+        export: Option<PathBuf>,
+        /// Pixels per seat in exported frames
+        #[arg(long, default_value_t = 4, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        scale: usize,
+    },
+    /// Time parse, part 1 and part 2 of one or all days
+    Bench {
+        /// Day to benchmark, all days when omitted
+        #[arg(long)]
+        day: Option<u8>,
+        /// Number of timed runs per day
+        #[arg(long, default_value_t = 20)]
+        iterations: usize,
+        /// Baseline JSON to compare the medians against
+        #[arg(long)]
+        baseline: Option<PathBuf>,
+        /// Overwrite the baseline with this run's numbers
+        #[arg(long, requires = "baseline")]
+        save_baseline: bool,
+        /// Instead of the days, step a synthetic SIZE x SIZE seat layout under
+        /// part 2's rule serially and in parallel and check both agree
+        #[arg(long, value_name = "SIZE", conflicts_with_all = ["day", "baseline"])]
+        seats: Option<usize>,
+        /// Generations to step with --seats
+        #[arg(long, default_value_t = 10, requires = "seats")]
+        generations: usize,
+        /// Threads for the parallel run with --seats, all cores when omitted
+        #[arg(long, requires = "seats")]
+        threads: Option<usize>,
+    },
+}
+
+impl Cli {
+    fn input_provider(&self) -> InputProvider {
+        let dir = self
+            .inputs
+            .clone()
+            .unwrap_or_else(InputProvider::default_dir);
+        let cache = self
+            .cache
+            .clone()
+            .unwrap_or_else(|| InputProvider::default_dir().join(".cache"));
+        let provider = InputProvider::new(dir).with_cache(cache);
+        match &self.session {
+            #[cfg(feature = "fetch")]
+            Some(session) => provider.with_fetcher(input::HttpFetcher::new(
+                input::HttpFetcher::BASE_URL,
+                session,
+            )),
+            _ => provider,
+        }
+    }
+}
+
+impl Cli {
+    /// `AOC_LOG` (in `env_logger` syntax, e.g. `AOC_LOG=day08=trace`) overrides the flags.
+    fn init_logging(&self) {
+        let level = match (self.quiet, self.verbose) {
+            (true, _) => LevelFilter::Error,
+            (false, 0) => LevelFilter::Warn,
+            (false, 1) => LevelFilter::Debug,
+            (false, _) => LevelFilter::Trace,
+        };
+        env_logger::Builder::new()
+            .filter_level(level)
+            .parse_env("AOC_LOG")
+            .format_timestamp(None)
+            .init();
+    }
+
+    fn answers_path(&self) -> PathBuf {
+        self.answers
+            .clone()
+            .unwrap_or_else(AnswerStore::default_path)
+    }
+}
+
+fn fail<E: Display>(e: E) -> ! {
+    eprintln!("{}", e);
+    process::exit(1);
+}
+
+fn selected_days(day: Option<u8>) -> Vec<Day> {
+    match day {
+        Some(number) => match problems::find(number) {
+            Some(day) => vec![day],
+            None => {
+                eprintln!("Day {} is not implemented", number);
+                process::exit(2);
+            }
+        },
+        None => problems::all(),
+    }
+}
+
+/// Recorded answers belong to the puzzle inputs, so answers for any other
+/// input can't be judged against them.
+fn check(
+    store: &AnswerStore,
+    day: u8,
+    part: u8,
+    answer: &Answer,
+    input: Option<&PathBuf>,
+) -> Verdict {
+    match input {
+        Some(_) => Verdict::Unknown,
+        None => store.check(day, part, answer),
+    }
+}
+
+fn solve(day: &Day, raw: &RawInput, part: u8) -> error::Result<(Answer, Duration)> {
+    let start = Instant::now();
+    let answer = day.solution.run(&raw.lines, part)?;
+    Ok((answer, start.elapsed()))
+}
 
 fn main() {
-    day11::solve()
+    let cli = Cli::parse();
+    cli.init_logging();
+    let provider = cli.input_provider();
+    let answers_path = cli.answers_path();
+    let mut store = AnswerStore::load(&answers_path).unwrap_or_else(|e| fail(e));
+    match cli.command {
+        Command::Run {
+            day,
+            part,
+            input,
+            record,
+        } => {
+            let mut reporter = Reporter::new(cli.format, io::stdout()).unwrap_or_else(|e| fail(e));
+            let parts = part.map_or(vec![1, 2], |p| vec![p]);
+            for day in selected_days(day).iter() {
+                let source = input
+                    .as_ref()
+                    .map_or(Source::Day(day.number), |p| Source::from_arg(p));
+                let raw = provider.load(&source).unwrap_or_else(|e| fail(e));
+                for &part in parts.iter() {
+                    let (answer, elapsed) =
+                        solve(day, &raw, part).unwrap_or_else(|e| fail(e.in_file(&raw.name)));
+                    if record {
+                        store.record(day.number, part, &answer);
+                    }
+                    let verdict = check(&store, day.number, part, &answer, input.as_ref());
+                    let checksum = raw.checksum();
+                    reporter
+                        .record(&Record {
+                            day: day.number,
+                            part,
+                            answer,
+                            elapsed,
+                            checksum,
+                            verdict,
+                        })
+                        .unwrap_or_else(|e| fail(e));
+                }
+            }
+            reporter.finish().unwrap_or_else(|e| fail(e));
+            if record {
+                store.save(&answers_path).unwrap_or_else(|e| fail(e));
+            }
+        }
+        Command::Verify => {
+            let mut reporter = Reporter::new(cli.format, io::stdout()).unwrap_or_else(|e| fail(e));
+            let mut failures = 0;
+            for day in problems::all().iter() {
+                let raw = match provider.load(&Source::Day(day.number)) {
+                    Ok(raw) => raw,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        failures += 1;
+                        continue;
+                    }
+                };
+                for part in 1..=2 {
+                    match solve(day, &raw, part) {
+                        Ok((answer, elapsed)) => {
+                            let verdict = store.check(day.number, part, &answer);
+                            if verdict.is_wrong() {
+                                failures += 1;
+                            }
+                            let checksum = raw.checksum();
+                            reporter
+                                .record(&Record {
+                                    day: day.number,
+                                    part,
+                                    answer,
+                                    elapsed,
+                                    checksum,
+                                    verdict,
+                                })
+                                .unwrap_or_else(|e| fail(e));
+                        }
+                        Err(e) => {
+                            eprintln!("{}", e.in_file(&raw.name));
+                            failures += 1;
+                        }
+                    }
+                }
+            }
+            reporter.finish().unwrap_or_else(|e| fail(e));
+            if failures > 0 {
+                eprintln!("{} failure(s)", failures);
+                process::exit(1);
+            }
+        }
+        Command::Debug { input, commands } => {
+            let source = input
+                .as_ref()
+                .map_or(Source::Day(8), |p| Source::from_arg(p));
+            let raw = provider.load(&source).unwrap_or_else(|e| fail(e));
+            let program = Day08
+                .parse(&raw.lines)
+                .unwrap_or_else(|e| fail(e.in_file(&raw.name)));
+            let mut debugger = Debugger::new(&program);
+            let mut out = io::stdout();
+            match commands {
+                Some(path) => {
+                    let name = path.display().to_string();
+                    let script =
+                        fs::File::open(&path).unwrap_or_else(|e| fail(error::Error::io(&name, e)));
+                    debugger.run(io::BufReader::new(script), &mut out, false)
+                }
+                None => {
+                    let stdin = io::stdin();
+                    let interactive = stdin.is_terminal();
+                    debugger.run(stdin.lock(), &mut out, interactive)
+                }
+            }
+            .unwrap_or_else(|e| fail(e));
+        }
+        Command::Disasm { input } => {
+            let source = input
+                .as_ref()
+                .map_or(Source::Day(8), |p| Source::from_arg(p));
+            let raw = provider.load(&source).unwrap_or_else(|e| fail(e));
+            let program = Day08
+                .parse(&raw.lines)
+                .unwrap_or_else(|e| fail(e.in_file(&raw.name)));
+            print!("{}", vm::listing::disassemble(&program));
+        }
+        Command::Analyze { input } => {
+            let source = input
+                .as_ref()
+                .map_or(Source::Day(8), |p| Source::from_arg(p));
+            let raw = provider.load(&source).unwrap_or_else(|e| fail(e));
+            let program = Day08
+                .parse(&raw.lines)
+                .unwrap_or_else(|e| fail(e.in_file(&raw.name)));
+            print!("{}", vm::analysis::report(&program));
+        }
+        Command::Trace { input } => {
+            let source = input
+                .as_ref()
+                .map_or(Source::Day(8), |p| Source::from_arg(p));
+            let raw = provider.load(&source).unwrap_or_else(|e| fail(e));
+            let program = Day08
+                .parse(&raw.lines)
+                .unwrap_or_else(|e| fail(e.in_file(&raw.name)));
+            let trace = day08::trace_program(&program);
+            match cli.format {
+                Format::Text => print!("{}", trace.to_text()),
+                Format::Json => println!("{}", trace.to_json()),
+                Format::Csv | Format::Tap => {
+                    eprintln!("traces are printed as text or json");
+                    process::exit(2);
+                }
+            }
+        }
+        Command::Repair {
+            input,
+            edits,
+            max_edits,
+        } => {
+            let source = input
+                .as_ref()
+                .map_or(Source::Day(8), |p| Source::from_arg(p));
+            let raw = provider.load(&source).unwrap_or_else(|e| fail(e));
+            let program = Day08
+                .parse(&raw.lines)
+                .unwrap_or_else(|e| fail(e.in_file(&raw.name)));
+            match day08::search_repair(&program, edits, max_edits) {
+                Some((mutations, accumulator)) => {
+                    for mutation in mutations.iter() {
+                        println!("{}", mutation);
+                    }
+                    println!("terminates with accumulator {}", accumulator);
+                }
+                None => {
+                    eprintln!("no repair with up to {} edit(s)", max_edits);
+                    process::exit(1);
+                }
+            }
+        }
+        Command::Animate {
+            part,
+            input,
+            delay,
+            export,
+            scale,
+        } => {
+            let source = input
+                .as_ref()
+                .map_or(Source::Day(11), |p| Source::from_arg(p));
+            let raw = provider.load(&source).unwrap_or_else(|e| fail(e));
+            let layout = Day11
+                .parse(&raw.lines)
+                .unwrap_or_else(|e| fail(e.in_file(&raw.name)));
+            let frames = layout.evolution(part);
+            let delay = Duration::from_millis(delay);
+            match export {
+                Some(path) if path.extension().is_some_and(|ext| ext == "gif") => {
+                    let name = path.display().to_string();
+                    fs::File::create(&path)
+                        .map(io::BufWriter::new)
+                        .and_then(|file| animate::write_gif(&frames, scale, delay, file))
+                        .unwrap_or_else(|e| fail(error::Error::io(&name, e)));
+                }
+                Some(dir) => animate::export_ppm(&frames, scale, &dir).unwrap_or_else(|e| fail(e)),
+                None => {
+                    animate::play(&frames, delay, &mut io::stdout()).unwrap_or_else(|e| fail(e))
+                }
+            }
+        }
+        Command::Bench {
+            seats: Some(size),
+            generations,
+            threads,
+            ..
+        } => {
+            let threads =
+                threads.unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+            let parity = bench::seats_parity(size, generations, threads);
+            println!("{}x{} seats, {} generations", size, size, generations);
+            println!(
+                "{:<10}{:>12}",
+                "serial",
+                Nanos(parity.serial.as_nanos() as u64).to_string()
+            );
+            println!(
+                "{:<10}{:>12} ({} threads)",
+                "parallel",
+                Nanos(parity.parallel.as_nanos() as u64).to_string(),
+                parity.threads
+            );
+            if !parity.identical {
+                eprintln!("parallel run diverged from the serial one");
+                process::exit(1);
+            }
+        }
+        Command::Bench {
+            day,
+            iterations,
+            baseline,
+            save_baseline,
+            ..
+        } => {
+            let previous = match &baseline {
+                Some(path) if path.is_file() => {
+                    Some(Baseline::load(path).unwrap_or_else(|e| fail(e)))
+                }
+                _ => None,
+            };
+            let mut current = Baseline::default();
+            println!(
+                "{:<7}{:<7}{:>12}{:>12}{:>12}{:>10}",
+                "day", "phase", "min", "median", "p95", "change"
+            );
+            for day in selected_days(day).iter() {
+                let raw = provider
+                    .load(&Source::Day(day.number))
+                    .unwrap_or_else(|e| fail(e));
+                let stats = bench::bench_day(day, &raw.lines, iterations)
+                    .unwrap_or_else(|e| fail(e.in_file(&raw.name)));
+                for phase in bench::PHASES.iter() {
+                    let s = &stats[*phase];
+                    let change = previous
+                        .as_ref()
+                        .and_then(|b| b.get(day.number, phase))
+                        .map_or(String::new(), |b| bench::change(s, b));
+                    println!(
+                        "{:<7}{:<7}{:>12}{:>12}{:>12}{:>10}",
+                        Baseline::key(day.number),
+                        phase,
+                        Nanos(s.min_ns).to_string(),
+                        Nanos(s.median_ns).to_string(),
+                        Nanos(s.p95_ns).to_string(),
+                        change
+                    );
+                }
+                current.days.insert(Baseline::key(day.number), stats);
+            }
+            if let (Some(path), true) = (&baseline, save_baseline) {
+                // days that were not benchmarked this time keep their old numbers
+                let mut saved = previous.unwrap_or_default();
+                saved.days.extend(current.days);
+                saved.save(path).unwrap_or_else(|e| fail(e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_inputs_are_not_checked() {
+        let mut store = AnswerStore::default();
+        store.record(8, 1, &Answer::from(1594));
+        let example = PathBuf::from("example.txt");
+        assert_eq!(
+            Verdict::Unknown,
+            check(&store, 8, 1, &Answer::from(5), Some(&example))
+        );
+        assert!(check(&store, 8, 1, &Answer::from(5), None).is_wrong());
+    }
+
+    #[test]
+    fn explicit_inputs_are_not_recorded() {
+        let args = [
+            "adventofcode2020",
+            "run",
+            "--day",
+            "8",
+            "--input",
+            "example.txt",
+            "--record",
+        ];
+        assert!(Cli::try_parse_from(args).is_err());
+        assert!(Cli::try_parse_from(["adventofcode2020", "run", "--day", "8", "--record"]).is_ok());
+    }
+
+    #[test]
+    fn frames_need_pixels() {
+        assert!(Cli::try_parse_from(["adventofcode2020", "animate", "--scale", "0"]).is_err());
+        assert!(Cli::try_parse_from(["adventofcode2020", "animate", "--scale", "1"]).is_ok());
+    }
+
+    #[test]
+    fn repair_edits() {
+        let args = [
+            "adventofcode2020",
+            "repair",
+            "--edits",
+            "flip,adjust=2,insert=nop +0",
+        ];
+        match Cli::try_parse_from(args).unwrap().command {
+            Command::Repair {
+                edits, max_edits, ..
+            } => {
+                assert_eq!(3, edits.len());
+                assert_eq!(Operator::Adjust(2), edits[1]);
+                assert_eq!(1, max_edits);
+            }
+            _ => panic!("expected the repair command"),
+        }
+        assert!(
+            Cli::try_parse_from(["adventofcode2020", "repair", "--edits", "adjust=0"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["adventofcode2020", "repair", "--edits", "shuffle"]).is_err());
+    }
 }
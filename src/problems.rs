@@ -1,11 +1,157 @@
-// pub mod day01;
-// pub mod day02;
-// pub mod day03;
-// pub mod day04;
-// pub mod day05;
-// pub mod day06;
-// pub mod day07;
-// pub mod day08;
-// pub mod day09;
-// pub mod day10;
+use super::error::Result;
+use std::fmt;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+pub mod day01;
+pub mod day02;
+pub mod day03;
+pub mod day04;
+pub mod day05;
+pub mod day06;
+pub mod day07;
+pub mod day08;
+pub mod day09;
+pub mod day10;
 pub mod day11;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    Number(i64),
+    Text(String),
+    Unsolved,
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Answer::Number(n) => write!(f, "{}", n),
+            Answer::Text(s) => write!(f, "{}", s),
+            Answer::Unsolved => write!(f, "unsolved"),
+        }
+    }
+}
+
+macro_rules! answer_from_integer {
+    ($($t:ty),*) => {
+        $(impl From<$t> for Answer {
+            fn from(n: $t) -> Self {
+                use std::convert::TryFrom;
+                i64::try_from(n).map_or_else(|_| Answer::Text(n.to_string()), Answer::Number)
+            }
+        })*
+    };
+}
+
+answer_from_integer!(i32, i64, isize, u32, u64, u128, usize);
+
+impl From<String> for Answer {
+    fn from(s: String) -> Self {
+        Answer::Text(s)
+    }
+}
+
+impl<T: Into<Answer>> From<Option<T>> for Answer {
+    fn from(maybe: Option<T>) -> Self {
+        maybe.map_or(Answer::Unsolved, Into::into)
+    }
+}
+
+/// A day's puzzle: parsing the input once and solving both parts from it.
+pub trait Solution {
+    type Input;
+
+    fn parse(&self, strings: &[String]) -> Result<Self::Input>;
+    fn part1(&self, input: &Self::Input) -> Answer;
+    fn part2(&self, input: &Self::Input) -> Answer;
+}
+
+/// Wall-clock time spent in each phase of a single run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timings {
+    pub parse: Duration,
+    pub part1: Duration,
+    pub part2: Duration,
+}
+
+/// Object-safe view of a `Solution` so days with different inputs can live in one registry.
+pub trait Runnable {
+    fn run(&self, strings: &[String], part: u8) -> Result<Answer>;
+    fn time(&self, strings: &[String]) -> Result<Timings>;
+}
+
+impl<S: Solution> Runnable for S {
+    fn run(&self, strings: &[String], part: u8) -> Result<Answer> {
+        let input = self.parse(strings)?;
+        Ok(match part {
+            1 => self.part1(&input),
+            2 => self.part2(&input),
+            _ => Answer::Unsolved,
+        })
+    }
+
+    fn time(&self, strings: &[String]) -> Result<Timings> {
+        let start = Instant::now();
+        let input = black_box(self.parse(strings)?);
+        let parse = start.elapsed();
+        let start = Instant::now();
+        black_box(self.part1(&input));
+        let part1 = start.elapsed();
+        let start = Instant::now();
+        black_box(self.part2(&input));
+        let part2 = start.elapsed();
+        Ok(Timings {
+            parse,
+            part1,
+            part2,
+        })
+    }
+}
+
+pub struct Day {
+    pub number: u8,
+    pub solution: Box<dyn Runnable>,
+}
+
+impl Day {
+    fn new<S: Solution + 'static>(number: u8, solution: S) -> Self {
+        Self {
+            number,
+            solution: Box::new(solution),
+        }
+    }
+}
+
+pub fn all() -> Vec<Day> {
+    vec![
+        Day::new(1, day01::Day01),
+        Day::new(2, day02::Day02),
+        Day::new(3, day03::Day03),
+        Day::new(4, day04::Day04),
+        Day::new(5, day05::Day05),
+        Day::new(6, day06::Day06),
+        Day::new(7, day07::Day07),
+        Day::new(8, day08::Day08),
+        Day::new(9, day09::Day09 { preamble_len: 25 }),
+        Day::new(10, day10::Day10),
+        Day::new(11, day11::Day11),
+    ]
+}
+
+pub fn find(number: u8) -> Option<Day> {
+    all().into_iter().find(|d| d.number == number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answer_conversions() {
+        assert_eq!(Answer::Number(42), Answer::from(42usize));
+        assert_eq!(Answer::Number(-7), Answer::from(-7i32));
+        assert_eq!(Answer::Unsolved, Answer::from(None::<u64>));
+        assert_eq!(Answer::Text(u64::MAX.to_string()), Answer::from(u64::MAX));
+        assert_eq!("123", Answer::from(Some(123u64)).to_string());
+    }
+}
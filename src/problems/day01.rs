@@ -1,20 +1,30 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader, Error};
-use std::path::Path;
+use super::super::error::Result;
+use super::super::utils::parse_as;
+use super::{Answer, Solution};
 
-fn read_numbers_from_file(s: &str) -> Result<Vec<i32>, Error> {
-    let path = Path::new(s);
-    let input = File::open(path)?;
-    let buffered = BufReader::new(input);
-    let numbers: Vec<i32> = buffered
-        .lines()
-        .map(|l| l.unwrap())
-        .map(|l| l.parse::<i32>().unwrap_or(0))
-        .collect();
-    Ok(numbers)
+pub struct Day01;
+
+impl Solution for Day01 {
+    type Input = Vec<i32>;
+
+    fn parse(&self, strings: &[String]) -> Result<Self::Input> {
+        parse_as::<i32>(strings)
+    }
+
+    fn part1(&self, numbers: &Self::Input) -> Answer {
+        find_two_numbers_adding_to(numbers, 2020)
+            .map(|(n1, n2)| n1 * n2)
+            .into()
+    }
+
+    fn part2(&self, numbers: &Self::Input) -> Answer {
+        find_three_numbers_adding_to(numbers, 2020)
+            .map(|(n1, n2, n3)| n1 * n2 * n3)
+            .into()
+    }
 }
 
-fn find_two_numbers_adding_to(numbers: &Vec<i32>, sum: i32) -> Option<(i32, i32)> {
+fn find_two_numbers_adding_to(numbers: &[i32], sum: i32) -> Option<(i32, i32)> {
     for n1 in numbers.iter() {
         for n2 in numbers.iter() {
             if n1 + n2 == sum {
@@ -25,7 +35,7 @@ fn find_two_numbers_adding_to(numbers: &Vec<i32>, sum: i32) -> Option<(i32, i32)
     None
 }
 
-fn find_three_numbers_adding_to(numbers: &Vec<i32>, sum: i32) -> Option<(i32, i32, i32)> {
+fn find_three_numbers_adding_to(numbers: &[i32], sum: i32) -> Option<(i32, i32, i32)> {
     for n1 in numbers.iter() {
         for n2 in numbers.iter() {
             for n3 in numbers.iter() {
@@ -37,13 +47,3 @@ fn find_three_numbers_adding_to(numbers: &Vec<i32>, sum: i32) -> Option<(i32, i3
     }
     None
 }
-
-pub fn day01_problem01() -> Result<(), Error> {
-    let numbers =
-        read_numbers_from_file("./inputs/day01_1").expect("Could not find or parse the input file");
-    let result01 = find_two_numbers_adding_to(&numbers, 2020).unwrap();
-    println!("Problem1: {:?}", result01.0 * result01.1);
-    let result02 = find_three_numbers_adding_to(&numbers, 2020).unwrap();
-    println!("Problem2: {:?}", result02.0 * result02.1 * result02.2);
-    Ok(())
-}
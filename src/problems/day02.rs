@@ -1,52 +1,75 @@
-// use regex::Regex;
-use std::convert::TryInto;
-use std::fs::File;
-use std::io::{BufRead, BufReader, Error};
+use super::super::error::{Error, Result};
+use super::super::utils::parse_lines;
+use super::{Answer, Solution};
 use std::num::ParseIntError;
-use std::path::Path;
 use std::str::FromStr;
 
-// #[derive(Debug, PartialEq)]
-// struct Password {
-//     password: String,
-// }
-//
-// impl Password {
-//     fn count_occurrences(&self, ch: char) -> usize {
-//         self.password.chars().filter(|&c| c == ch).count()
-//     }
-// }
+pub struct Day02;
+
+impl Solution for Day02 {
+    type Input = Vec<(PasswordPolicy, String)>;
+
+    fn parse(&self, strings: &[String]) -> Result<Self::Input> {
+        parse_password_policy_pairs(strings)
+    }
+
+    fn part1(&self, list: &Self::Input) -> Answer {
+        list.iter().filter(|(pp, p)| pp.satisfies(p)).count().into()
+    }
+
+    fn part2(&self, list: &Self::Input) -> Answer {
+        list.iter()
+            .filter(|(pp, p)| pp.is_satisfied_by(p))
+            .count()
+            .into()
+    }
+}
 
 #[derive(Debug, PartialEq)]
-struct PasswordPolicy {
+pub struct PasswordPolicy {
     min_occurrences: usize,
     max_occurrences: usize,
     character: char,
 }
 
 impl PasswordPolicy {
-    // fn satisfies(&self, password: &Password) -> bool {
-    //     let actual_char_count = password.count_occurrences(self.character);
-    //     actual_char_count >= self.min_occurrences && actual_char_count <= self.max_occurrences
-    // }
+    fn satisfies(&self, password: &str) -> bool {
+        let actual_char_count = password.chars().filter(|&c| c == self.character).count();
+        actual_char_count >= self.min_occurrences && actual_char_count <= self.max_occurrences
+    }
 
     fn is_satisfied_by(&self, password: &str) -> bool {
-        let first = password.chars().nth(self.min_occurrences - 1) == Some(self.character);
-        let second = password.chars().nth(self.max_occurrences - 1) == Some(self.character);
-        return (first & !second) | (!first & second);
+        let at = |position: usize| {
+            position
+                .checked_sub(1)
+                .and_then(|i| password.chars().nth(i))
+        };
+        let first = at(self.min_occurrences) == Some(self.character);
+        let second = at(self.max_occurrences) == Some(self.character);
+        first ^ second
     }
 }
 
 impl FromStr for PasswordPolicy {
-    type Err = ParseIntError;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Vec<&str> = s.split(' ').collect();
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let malformed = || Error::parse(format!("expected policy like \"1-3 a\", got {:?}", s));
+        let (range, character) = s.split_once(' ').ok_or_else(malformed)?;
+        let (min_str, max_str) = range.split_once('-').ok_or_else(malformed)?;
 
-        let minmax: Vec<&str> = parts[0].split('-').collect();
-
-        let min = minmax[0].parse::<usize>()?;
-        let max = minmax[1].parse::<usize>()?;
-        let char = parts[1].chars().nth(0).unwrap_or(' ');
+        let min = parse_position(min_str).map_err(|e| e.at_column(1))?;
+        let max = parse_position(max_str).map_err(|e| e.at_column(min_str.len() + 2))?;
+        let mut chars = character.chars();
+        let char = match (chars.next(), chars.next()) {
+            (Some(c), None) => c,
+            _ => {
+                return Err(Error::parse(format!(
+                    "expected a single character, got {:?}",
+                    character
+                ))
+                .at_column(range.len() + 2))
+            }
+        };
         Ok(PasswordPolicy {
             min_occurrences: min,
             max_occurrences: max,
@@ -55,38 +78,65 @@ impl FromStr for PasswordPolicy {
     }
 }
 
-fn parse_password_policy_pair(s: &str) -> Option<(PasswordPolicy, String)> {
-    let parts: Vec<&str> = s.split(':').collect();
-    let policy = parts[0].parse::<PasswordPolicy>().unwrap();
-    let password = parts[1].trim().to_string();
-    return Some((policy, password));
+fn parse_position(s: &str) -> Result<usize> {
+    s.parse::<usize>()
+        .map_err(|e: ParseIntError| Error::parse(format!("invalid number {:?}: {}", s, e)))
 }
 
-fn read_password_policy_pairs_from_file(s: &str) -> Result<Vec<(PasswordPolicy, String)>, Error> {
-    let path = Path::new(s);
-    let input = File::open(path)?;
-    let list: Vec<(PasswordPolicy, String)> = BufReader::new(input)
-        .lines()
-        .map(|l| l.unwrap())
-        .map(|l| parse_password_policy_pair(&l).unwrap())
-        .collect();
-    Ok(list)
+fn parse_password_policy_pair(s: &str) -> Result<(PasswordPolicy, String)> {
+    let (policy, password) = s
+        .split_once(':')
+        .ok_or_else(|| Error::parse(format!("expected \"<policy>: <password>\", got {:?}", s)))?;
+    let policy = policy.parse::<PasswordPolicy>()?;
+    let password = password.trim().to_string();
+    Ok((policy, password))
 }
-pub fn day02() {
-    let p = "10-19 v".parse::<PasswordPolicy>().unwrap();
-    assert_eq!('v', p.character);
-    assert_eq!(10, p.min_occurrences);
-    assert_eq!(19, p.max_occurrences);
-    println!("{:?}", p);
-    assert!("5-8 h"
-        .parse::<PasswordPolicy>()
-        .unwrap()
-        .is_satisfied_by("tfhhtsjhhhsddl"));
-
-    let list =
-        read_password_policy_pairs_from_file("./inputs/day02_1").expect("Could not read file");
-    // let n_satisfies = list.iter().filter(|(pp, p)| pp.satisfies(&p)).count();
-    // println!("{:?}", n_satisfies);
-    let n_satisfies2 = list.iter().filter(|(pp, p)| pp.is_satisfied_by(&p)).count();
-    println!("{:?}", n_satisfies2);
+
+fn parse_password_policy_pairs(strings: &[String]) -> Result<Vec<(PasswordPolicy, String)>> {
+    parse_lines(strings, parse_password_policy_pair)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_policy() {
+        let p = "10-19 v".parse::<PasswordPolicy>().unwrap();
+        assert_eq!('v', p.character);
+        assert_eq!(10, p.min_occurrences);
+        assert_eq!(19, p.max_occurrences);
+    }
+
+    #[test]
+    fn positional_policy() {
+        assert!("5-8 h"
+            .parse::<PasswordPolicy>()
+            .unwrap()
+            .is_satisfied_by("tfhhtsjhhhsddl"));
+    }
+
+    #[test]
+    fn examples() {
+        let strings: Vec<String> = ["1-3 a: abcde", "1-3 b: cdefg", "2-9 c: ccccccccc"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let input = Day02.parse(&strings).unwrap();
+        assert_eq!(Answer::from(2), Day02.part1(&input));
+        assert_eq!(Answer::from(1), Day02.part2(&input));
+    }
+
+    #[test]
+    fn malformed_policy() {
+        let strings: Vec<String> = ["1-3 a: abcde", "1-x b: cdefg"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let e = Day02.parse(&strings).unwrap_err();
+        assert_eq!(
+            "line 2:3: invalid number \"x\": invalid digit found in string",
+            e.to_string()
+        );
+    }
 }
@@ -1,8 +1,36 @@
-use super::super::utils::read_strings_from_file;
-use itertools::Itertools;
+use super::super::error::{Error, Result};
+use super::super::utils::groups;
+use super::{Answer, Solution};
 use regex::Regex;
 use std::collections::HashMap;
 
+pub struct Day04;
+
+impl Solution for Day04 {
+    type Input = Vec<Passport>;
+
+    fn parse(&self, strings: &[String]) -> Result<Self::Input> {
+        parse_strings_into_passports(strings)
+    }
+
+    fn part1(&self, passports: &Self::Input) -> Answer {
+        passports
+            .iter()
+            .filter(|&p| p.has_required_fields())
+            .count()
+            .into()
+    }
+
+    fn part2(&self, passports: &Self::Input) -> Answer {
+        let validator = PassportValidator::new();
+        passports
+            .iter()
+            .filter(|&p| validator.is_valid(p))
+            .count()
+            .into()
+    }
+}
+
 trait ValueValidator {
     fn is_valid(&self, maybe_value: Option<&str>) -> bool;
 }
@@ -21,7 +49,7 @@ struct MatchingRegexRule {
 }
 impl ValueValidator for MatchingRegexRule {
     fn is_valid(&self, maybe_value: Option<&str>) -> bool {
-        maybe_value.map_or(false, |s| self.regex.captures(s).is_some())
+        maybe_value.is_some_and(|s| self.regex.captures(s).is_some())
     }
 }
 fn is_matching_regex(re: &str) -> Box<dyn ValueValidator> {
@@ -36,15 +64,11 @@ struct YearInRangeRule {
 }
 impl ValueValidator for YearInRangeRule {
     fn is_valid(&self, maybe_value: Option<&str>) -> bool {
-        maybe_value.map_or(false, |s| {
-            self.year_regex.captures(s).map_or(false, |cap| {
-                cap.get(1).map_or(false, |digits| {
+        maybe_value.is_some_and(|s| {
+            self.year_regex.captures(s).is_some_and(|cap| {
+                cap.get(1).is_some_and(|digits| {
                     let year = digits.as_str().parse::<i32>().unwrap();
-                    if year >= self.from_year && year <= self.to_year {
-                        true
-                    } else {
-                        false
-                    }
+                    year >= self.from_year && year <= self.to_year
                 })
             })
         })
@@ -64,23 +88,18 @@ struct HeightRule {}
 impl ValueValidator for HeightRule {
     fn is_valid(&self, maybe_value: Option<&str>) -> bool {
         let regex = Regex::new(r"^(\d+)(cm|in)$").unwrap();
-        maybe_value.map_or(false, |s| {
-            regex.captures(s).map_or(false, |cap| {
-                cap.get(1).map_or(false, |digits| {
-                    cap.get(2).map_or(false, |unit| {
-                        let val = digits.as_str().parse::<i32>().unwrap();
+        maybe_value.is_some_and(|s| {
+            regex.captures(s).is_some_and(|cap| {
+                cap.get(1).is_some_and(|digits| {
+                    cap.get(2).is_some_and(|unit| {
+                        let val = match digits.as_str().parse::<i32>() {
+                            Ok(val) => val,
+                            Err(_) => return false,
+                        };
                         if unit.as_str() == "cm" {
-                            if val >= 150 && val <= 193 {
-                                true
-                            } else {
-                                false
-                            }
+                            (150..=193).contains(&val)
                         } else if unit.as_str() == "in" {
-                            if val >= 59 && val <= 76 {
-                                true
-                            } else {
-                                false
-                            }
+                            (59..=76).contains(&val)
                         } else {
                             false
                         }
@@ -125,32 +144,35 @@ impl PassportValidator {
     fn is_field_valid(&self, field: &str, value: Option<&str>) -> bool {
         self.rules
             .get(field)
-            .map_or(true, |rule| rule.is_valid(value))
+            .is_none_or(|rule| rule.is_valid(value))
     }
 
     fn is_valid(&self, passport: &Passport) -> bool {
         self.rules
             .keys()
-            .map(|&key| self.is_field_valid(key, passport.get(key)))
-            .all(|v| v)
+            .all(|&key| self.is_field_valid(key, passport.get(key)))
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
-struct Passport {
+pub struct Passport {
     statements: Vec<(String, String)>,
 }
 
 impl Passport {
-    fn from_statements(ss: &Vec<&str>) -> Passport {
+    #[cfg(test)]
+    fn from_statements(ss: &[&str]) -> Result<Passport> {
         let statements = ss
             .iter()
-            .map(|&s| {
-                let pair: Vec<&str> = s.split(':').collect();
-                (pair[0].to_string(), pair[1].to_string())
-            })
-            .collect();
-        Self { statements }
+            .map(|&s| parse_statement(s))
+            .collect::<Result<Vec<(String, String)>>>()?;
+        Ok(Self { statements })
+    }
+
+    fn has_required_fields(&self) -> bool {
+        ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"]
+            .iter()
+            .all(|&key| self.get(key).is_some())
     }
 
     fn get(&self, key: &str) -> Option<&str> {
@@ -161,81 +183,110 @@ impl Passport {
     }
 }
 
-fn parse_strings_into_passports(strings: &Vec<String>) -> Vec<Passport> {
-    strings
-        .iter()
-        .flat_map(|s| s.split(' '))
-        .batching(|it| {
-            let mut batch: Vec<&str> = Vec::new();
-            while let Some(el) = it.next() {
-                if el.trim().len() == 0 {
-                    return Some(batch);
-                } else {
-                    batch.push(el.trim());
-                }
-            }
-            if batch.len() > 0 {
-                Some(batch)
-            } else {
-                None
-            }
+fn parse_statement(s: &str) -> Result<(String, String)> {
+    s.split_once(':')
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .ok_or_else(|| Error::parse(format!("expected \"key:value\", got {:?}", s)))
+}
+
+fn parse_strings_into_passports(strings: &[String]) -> Result<Vec<Passport>> {
+    groups(strings)
+        .map(|group| {
+            let statements = group
+                .numbered_lines()
+                .flat_map(|(line, s)| s.split_whitespace().map(move |el| (line, el)))
+                .map(|(line, el)| parse_statement(el).map_err(|e| e.at_line(line)))
+                .collect::<Result<Vec<(String, String)>>>()?;
+            Ok(Passport { statements })
         })
-        .map(|vs| Passport::from_statements(&vs))
         .collect()
 }
 
-pub fn solve() {
-    let validator = PassportValidator::new();
-    // byr
-    assert!(!validator.is_field_valid("byr", None));
-    assert!(!validator.is_field_valid("byr", Some("asdf")));
-    assert!(!validator.is_field_valid("byr", Some("1919")));
-    assert!(!validator.is_field_valid("byr", Some("2003")));
-    assert!(validator.is_field_valid("byr", Some("1920")));
-    assert!(validator.is_field_valid("byr", Some("2000")));
-    assert!(validator.is_field_valid("byr", Some("2002")));
-
-    // iyr
-    assert!(!validator.is_field_valid("iyr", None));
-    assert!(!validator.is_field_valid("iyr", Some("asdf")));
-    assert!(!validator.is_field_valid("iyr", Some("2009")));
-    assert!(!validator.is_field_valid("iyr", Some("2021")));
-    assert!(validator.is_field_valid("iyr", Some("2010")));
-    assert!(validator.is_field_valid("iyr", Some("2015")));
-    assert!(validator.is_field_valid("iyr", Some("2020")));
-
-    assert!(!validator.is_field_valid("hgt", Some("100in")));
-    assert!(!validator.is_field_valid("hgt", Some("200cm")));
-    assert!(validator.is_field_valid("hgt", Some("170cm")));
-
-    assert!(validator.is_field_valid("hcl", Some("#1234ac")));
-    assert!(!validator.is_field_valid("hcl", Some("#1234acf")));
-
-    assert!(validator.is_field_valid("ecl", Some("oth")));
-    assert!(!validator.is_field_valid("ecl", Some("123")));
-
-    assert!(validator.is_field_valid("pid", Some("123456789")));
-    assert!(!validator.is_field_valid("pid", Some("0234")));
-
-    let p1 = Passport::from_statements(&vec![
-        "byr:1921",
-        "iyr:2020",
-        "eyr:2020",
-        "hcl:#1234ad",
-        "ecl:amb",
-        "cid:336",
-        "hgt:182cm",
-        "pid:533626984",
-    ]);
-    assert!(validator.is_valid(&p1));
-    let strings = read_strings_from_file("./inputs/day04_1").expect("Failed to read inputs");
-    let passports: Vec<Passport> = parse_strings_into_passports(&strings);
-    let valid_passwords: Vec<&Passport> = passports
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validator() {
+        let validator = PassportValidator::new();
+        // byr
+        assert!(!validator.is_field_valid("byr", None));
+        assert!(!validator.is_field_valid("byr", Some("asdf")));
+        assert!(!validator.is_field_valid("byr", Some("1919")));
+        assert!(!validator.is_field_valid("byr", Some("2003")));
+        assert!(validator.is_field_valid("byr", Some("1920")));
+        assert!(validator.is_field_valid("byr", Some("2000")));
+        assert!(validator.is_field_valid("byr", Some("2002")));
+
+        // iyr
+        assert!(!validator.is_field_valid("iyr", None));
+        assert!(!validator.is_field_valid("iyr", Some("asdf")));
+        assert!(!validator.is_field_valid("iyr", Some("2009")));
+        assert!(!validator.is_field_valid("iyr", Some("2021")));
+        assert!(validator.is_field_valid("iyr", Some("2010")));
+        assert!(validator.is_field_valid("iyr", Some("2015")));
+        assert!(validator.is_field_valid("iyr", Some("2020")));
+
+        assert!(!validator.is_field_valid("hgt", Some("100in")));
+        assert!(!validator.is_field_valid("hgt", Some("200cm")));
+        assert!(validator.is_field_valid("hgt", Some("170cm")));
+        assert!(!validator.is_field_valid("hgt", Some("99999999999cm")));
+
+        assert!(validator.is_field_valid("hcl", Some("#1234ac")));
+        assert!(!validator.is_field_valid("hcl", Some("#1234acf")));
+
+        assert!(validator.is_field_valid("ecl", Some("oth")));
+        assert!(!validator.is_field_valid("ecl", Some("123")));
+
+        assert!(validator.is_field_valid("pid", Some("123456789")));
+        assert!(!validator.is_field_valid("pid", Some("0234")));
+
+        let p1 = Passport::from_statements(&[
+            "byr:1921",
+            "iyr:2020",
+            "eyr:2020",
+            "hcl:#1234ad",
+            "ecl:amb",
+            "cid:336",
+            "hgt:182cm",
+            "pid:533626984",
+        ])
+        .unwrap();
+        assert!(validator.is_valid(&p1));
+    }
+
+    #[test]
+    fn passports_across_lines() {
+        let strings: Vec<String> = [
+            "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd ",
+            "byr:1937 iyr:2017 cid:147 hgt:183cm",
+            "",
+            "iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884",
+            "hcl:#cfa07d byr:1929",
+            "",
+            "hcl:#ae17e1 iyr:2013",
+            "eyr:2024",
+            "ecl:brn pid:760753108 byr:1931",
+            "hgt:179cm",
+            "",
+            "hcl:#cfa07d eyr:2025 pid:166559648",
+            "iyr:2011 ecl:brn hgt:59in",
+        ]
         .iter()
-        .filter(|&p| validator.is_valid(p))
+        .map(|s| s.to_string())
         .collect();
+        let passports = Day04.parse(&strings).unwrap();
+        assert_eq!(4, passports.len());
+        assert_eq!(Answer::from(2), Day04.part1(&passports));
+    }
 
-    println!("{:?}", passports);
-    println!("{:?}", valid_passwords);
-    println!("{:?}", valid_passwords.len());
+    #[test]
+    fn malformed_statement() {
+        let strings: Vec<String> = ["byr:1937", "", "iyr:2017 hgt"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let e = Day04.parse(&strings).unwrap_err();
+        assert_eq!("line 3: expected \"key:value\", got \"hgt\"", e.to_string());
+    }
 }
@@ -1,56 +1,60 @@
-use super::super::utils::read_strings_from_file;
+use super::super::error::{Error, Result};
+use super::super::utils::parse_lines;
+use super::{Answer, Solution};
 use itertools::Itertools;
+use std::str::FromStr;
+
+pub struct Day05;
+
+impl Solution for Day05 {
+    type Input = Vec<BoardingPass>;
+
+    fn parse(&self, strings: &[String]) -> Result<Self::Input> {
+        parse_boarding_passes(strings)
+    }
+
+    fn part1(&self, passes: &Self::Input) -> Answer {
+        passes.iter().map(|pass| pass.seat_id).max().into()
+    }
+
+    fn part2(&self, passes: &Self::Input) -> Answer {
+        let mut sorted_passes = passes.to_vec();
+        sorted_passes.sort_by_key(|a| a.seat_id);
+        sorted_passes
+            .iter()
+            .tuple_windows::<(_, _)>()
+            .find(|w| w.0.seat_id < w.1.seat_id - 1)
+            .map(|(before_my_seat, _)| before_my_seat.seat_id + 1)
+            .into()
+    }
+}
 
 #[derive(Debug, Clone)]
-struct BoardingPass {
-    encoded: String,
-    binary: String,
+pub struct BoardingPass {
     seat_id: isize,
 }
 
-impl BoardingPass {
-    fn from_str(str: String) -> Self {
+impl FromStr for BoardingPass {
+    type Err = Error;
+
+    fn from_str(str: &str) -> Result<Self> {
         let binary = str
             .chars()
-            .map(|c| match c {
-                'F' => '0',
-                'B' => '1',
-                'R' => '1',
-                'L' => '0',
-                _ => '0',
+            .enumerate()
+            .map(|(i, c)| match c {
+                'F' => Ok('0'),
+                'B' => Ok('1'),
+                'R' => Ok('1'),
+                'L' => Ok('0'),
+                _ => Err(Error::parse(format!("unexpected {:?}", c)).at_column(i + 1)),
             })
-            .join("");
-        let seat_id = isize::from_str_radix(&binary, 2).unwrap();
-        Self {
-            encoded: str,
-            binary,
-            seat_id,
-        }
+            .collect::<Result<String>>()?;
+        let seat_id = isize::from_str_radix(&binary, 2)
+            .map_err(|e| Error::parse(format!("invalid boarding pass {:?}: {}", str, e)))?;
+        Ok(Self { seat_id })
     }
 }
 
-pub fn solve() {
-    let strings = read_strings_from_file("./inputs/day05_1").expect("Failed to read inputs");
-    let passes: Vec<BoardingPass> = strings
-        .iter()
-        .map(|s| BoardingPass::from_str(s.to_string()))
-        .collect();
-    let highest_seat_id = passes.iter().reduce(|state, pass| {
-        if pass.seat_id > state.seat_id {
-            pass
-        } else {
-            state
-        }
-    });
-    let mut sorted_passes = passes.to_vec();
-    sorted_passes.sort_by(|a, b| a.seat_id.cmp(&b.seat_id));
-    println!("{:?}", passes);
-    println!("{:?}", highest_seat_id);
-    println!("{:?}, {:?}", sorted_passes.first(), sorted_passes.last());
-    let my_seat_window = sorted_passes
-        .iter()
-        .tuple_windows::<(_, _, _)>()
-        .find(|w| (w.0.seat_id < w.1.seat_id - 1) || (w.1.seat_id < w.2.seat_id - 1))
-        .unwrap();
-    println!("{:?}", my_seat_window);
+fn parse_boarding_passes(strings: &[String]) -> Result<Vec<BoardingPass>> {
+    parse_lines(strings, str::parse)
 }
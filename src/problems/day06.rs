@@ -1,16 +1,45 @@
-use super::super::utils::read_strings_from_file;
+use super::super::error::Result;
+use super::super::utils::groups;
+use super::{Answer, Solution};
 use itertools::Itertools;
 use std::collections::HashSet;
-use std::hash::Hash;
 
-fn to_unique_chars(strs: &Vec<&str>) -> Vec<char> {
-    strs.iter().flat_map(|&s| s.chars()).unique().collect()
+pub struct Day06;
+
+impl Solution for Day06 {
+    type Input = Vec<Vec<String>>;
+
+    fn parse(&self, strings: &[String]) -> Result<Self::Input> {
+        Ok(parse_groups(strings))
+    }
+
+    fn part1(&self, groups: &Self::Input) -> Answer {
+        let anyone_answers: Vec<Vec<char>> =
+            groups.iter().map(|group| to_unique_chars(group)).collect();
+        anyone_answers.iter().map(|x| x.len()).sum::<usize>().into()
+    }
+
+    fn part2(&self, groups: &Self::Input) -> Answer {
+        let everyone_answers: Vec<HashSet<char>> = groups
+            .iter()
+            .map(|group| to_intersection_of_chars(group))
+            .collect();
+        everyone_answers
+            .iter()
+            .map(|x| x.len())
+            .sum::<usize>()
+            .into()
+    }
+}
+
+fn to_unique_chars(strs: &[String]) -> Vec<char> {
+    strs.iter().flat_map(|s| s.chars()).unique().collect()
 }
 
-fn to_intersection_of_chars(strs: &Vec<&str>) -> HashSet<char> {
+fn to_intersection_of_chars(strs: &[String]) -> HashSet<char> {
     let hss: Vec<HashSet<char>> = strs
         .iter()
-        .map(|&s| -> HashSet<char> { s.chars().collect() })
+        .map(|s| -> HashSet<char> { s.chars().collect() })
         .collect();
     //println!("Vec of HashSets: {:?}", hss);
     // reducing multiple HashSets to an intersection of all
@@ -20,38 +49,6 @@ fn to_intersection_of_chars(strs: &Vec<&str>) -> HashSet<char> {
     })
 }
 
-pub fn solve() {
-    let strings = read_strings_from_file("./inputs/day06_1").expect("Failed to read inputs");
-    let groups: Vec<Vec<&str>> = strings
-        .iter()
-        .batching(|it| {
-            let mut batch: Vec<&str> = Vec::new();
-            while let Some(el) = it.next() {
-                if el.trim().len() == 0 {
-                    return Some(batch);
-                } else {
-                    batch.push(el.trim());
-                }
-            }
-            if batch.len() > 0 {
-                Some(batch)
-            } else {
-                None
-            }
-        })
-        .collect();
-    let anyone_answers: Vec<Vec<char>> =
-        groups.iter().map(|group| to_unique_chars(group)).collect();
-    println!("{:?}", strings);
-    println!("{:?}", groups);
-    println!("{:?}", anyone_answers);
-    let n_anyone_answers_total: usize = anyone_answers.iter().map(|x| x.len()).sum();
-    println!("Problem 1 solution: {:?}", n_anyone_answers_total);
-    let everyone_answers: Vec<HashSet<char>> = groups
-        .iter()
-        .map(|group| to_intersection_of_chars(group))
-        .collect();
-    let n_everyone_answers_total: usize = everyone_answers.iter().map(|x| x.len()).sum();
-    println!("{:?}", everyone_answers);
-    println!("Problem 2 solution: {:?}", n_everyone_answers_total);
+fn parse_groups(strings: &[String]) -> Vec<Vec<String>> {
+    groups(strings).map(|group| group.lines).collect()
 }
@@ -1,66 +1,83 @@
-use super::super::utils::read_strings_from_file;
+use super::super::error::{Error, Result};
+use super::super::utils::parse_lines;
+use super::{Answer, Solution};
 use graphlib::{Graph, VertexId};
 use itertools::Itertools;
 use lazy_static::lazy_static;
+use log::{debug, trace};
 use regex::Regex;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-type RulesGraph = Graph<String>;
-
-pub fn solve() {
-    let strings = read_strings_from_file("./inputs/day07_1").expect("Failed to read inputs");
-    println!("Problem 1: {:?}", problem_1(&strings));
-    println!("Problem 2: {:?}", problem_2(&strings));
+/// Which bags each bag contains, with the counts kept beside the graph.
+pub struct RulesGraph {
+    graph: Graph<String>,
+    counts: HashMap<(VertexId, VertexId), usize>,
 }
 
-pub fn problem_1(strings: &Vec<String>) -> usize {
-    let rules: Vec<(String, Vec<(usize, String)>)> = strings
-        .iter()
-        .map(|s| parse_rule(s).unwrap())
-        .map(|(s, v)| (s.to_string(), parse_contained_bags(v)))
-        .collect();
-    println!("{:?}", rules);
-
-    let graph = build_graph(&rules);
-    let shiny_gold = find_node_in_graph(&graph, "shiny gold").unwrap();
-    println!("{:?}", shiny_gold);
-    let parents = find_all_parents_of_node(&graph, &shiny_gold);
-    println!("{:?}: {:?}", parents.len(), parents);
-    parents.len()
-}
+pub struct Day07;
+
+impl Solution for Day07 {
+    type Input = RulesGraph;
+
+    fn parse(&self, strings: &[String]) -> Result<Self::Input> {
+        let rules: Vec<(String, Vec<(usize, String)>)> = parse_lines(strings, |s| {
+            let (bag, contents) = parse_rule(s).ok_or_else(|| {
+                Error::parse(format!(
+                    "expected \"<bag> bags contain <contents>\", got {:?}",
+                    s
+                ))
+            })?;
+            Ok((bag.to_string(), parse_contained_bags(contents)?))
+        })?;
+        debug!("{:?}", rules);
+        build_graph(&rules)
+    }
+
+    fn part1(&self, graph: &Self::Input) -> Answer {
+        let shiny_gold = match find_node_in_graph(&graph.graph, "shiny gold") {
+            Some(node) => node,
+            None => return Answer::Unsolved,
+        };
+        debug!("{:?}", shiny_gold);
+        let parents = find_all_parents_of_node(graph, &shiny_gold);
+        debug!("{:?}: {:?}", parents.len(), parents);
+        parents.len().into()
+    }
 
-pub fn problem_2(strings: &Vec<String>) -> usize {
-    let rules: Vec<(String, Vec<(usize, String)>)> = strings
-        .iter()
-        .map(|s| parse_rule(s).unwrap())
-        .map(|(s, v)| (s.to_string(), parse_contained_bags(v)))
-        .collect();
-
-    let graph = build_graph(&rules);
-    let shiny_gold = find_node_in_graph(&graph, "shiny gold").unwrap();
-    let n_bags: usize = calculate_number_of_bags(&graph, &shiny_gold);
-    n_bags - 1 // -1 is because shiny_gold is not counted, we calculate the n of *other* bagsr
+    fn part2(&self, graph: &Self::Input) -> Answer {
+        let shiny_gold = match find_node_in_graph(&graph.graph, "shiny gold") {
+            Some(node) => node,
+            None => return Answer::Unsolved,
+        };
+        let n_bags: usize = calculate_number_of_bags(graph, &shiny_gold);
+        (n_bags - 1).into() // -1 is because shiny_gold is not counted, we calculate the n of *other* bagsr
+    }
 }
 
 fn calculate_number_of_bags(graph: &RulesGraph, node: &VertexId) -> usize {
     // if no children return 1
     // if children, for each child return sum(weight * calculate_number_of_bags(child))
-    let children: Vec<&VertexId> = graph.out_neighbors(node).collect();
-    let node_label = graph.fetch(node).cloned().unwrap();
-    if children.len() == 0 {
-        println!("{:?} -> 1", node_label);
-        return 1;
+    let children: Vec<&VertexId> = graph.graph.out_neighbors(node).collect();
+    let node_label = graph.graph.fetch(node).cloned().unwrap();
+    if children.is_empty() {
+        trace!("{:?} -> 1", node_label);
+        1
     } else {
         let node_bags: usize = children.iter()
             .map(|&child| {
-                let child_weight = (graph.weight(node, child).unwrap() * 100.0) as usize;
-                let child_capacity = calculate_number_of_bags(&graph, child);
-                println!("{:?} -> {:?}*{:?}", node_label, child_weight, child_capacity);
+                let child_weight = graph.counts[&(*node, *child)];
+                let child_capacity = calculate_number_of_bags(graph, child);
+                trace!(
+                    "{:?} -> {:?}*{:?}",
+                    node_label,
+                    child_weight,
+                    child_capacity
+                );
                 child_weight * child_capacity
             })
             .sum();
-        println!("{:?} = {:?}", node_label, node_bags + 1);
-        return node_bags + 1;
+        trace!("{:?} = {:?}", node_label, node_bags + 1);
+        node_bags + 1
     }
 }
 
@@ -72,19 +89,19 @@ fn find_all_parents_of_node<'a>(graph: &'a RulesGraph, node: &'a VertexId) -> Ve
         if v != node {
             parents.insert(v);
         }
-        graph.in_neighbors(v).for_each(|v| queue.push(v));
+        graph.graph.in_neighbors(v).for_each(|v| queue.push(v));
     }
     parents.into_iter().collect()
 }
 
-fn find_node_in_graph(graph: &RulesGraph, node_label: &str) -> Option<VertexId> {
+fn find_node_in_graph(graph: &Graph<String>, node_label: &str) -> Option<VertexId> {
     graph
         .vertices()
         .find(|&n| graph.fetch(n).unwrap() == node_label)
         .cloned()
 }
 
-fn upsert_node(graph: &mut RulesGraph, node_label: &str) -> VertexId {
+fn upsert_node(graph: &mut Graph<String>, node_label: &str) -> VertexId {
     if let Some(vertex) = find_node_in_graph(graph, node_label) {
         vertex
     } else {
@@ -92,17 +109,21 @@ fn upsert_node(graph: &mut RulesGraph, node_label: &str) -> VertexId {
     }
 }
 
-fn build_graph(rules: &Vec<(String, Vec<(usize, String)>)>) -> RulesGraph {
+/// One rule per input line, so errors point at the line of the rule.
+fn build_graph(rules: &[(String, Vec<(usize, String)>)]) -> Result<RulesGraph> {
     let mut graph: Graph<String> = Graph::new();
-    for (p, chs) in rules.iter() {
+    let mut counts = HashMap::new();
+    for (i, (p, chs)) in rules.iter().enumerate() {
         let id1 = upsert_node(&mut graph, p);
         for (w, ch) in chs.iter() {
             let id2 = upsert_node(&mut graph, ch);
-            // println!("Weight: {:?}, {:?}", (*w as f32)/100.0, *w as f32);
-            graph.add_edge_with_weight(&id1, &id2, (*w as f32)/100.0).expect("Could not add the edge");
+            graph.add_edge(&id1, &id2).map_err(|e| {
+                Error::parse(format!("cannot add {:?} to {:?}: {:?}", ch, p, e)).at_line(i + 1)
+            })?;
+            *counts.entry((id1, id2)).or_insert(0) += w;
         }
     }
-    graph
+    Ok(RulesGraph { graph, counts })
 }
 
 fn parse_rule(string: &str) -> Option<(&str, &str)> {
@@ -112,16 +133,18 @@ fn parse_rule(string: &str) -> Option<(&str, &str)> {
         .collect_tuple()
 }
 
-fn parse_contained_bags(string: &str) -> Vec<(usize, String)> {
+fn parse_contained_bags(string: &str) -> Result<Vec<(usize, String)>> {
     lazy_static! {
         static ref REGEX: Regex = Regex::new(r"(\d+) (\w+ \w+) bag").unwrap();
     }
     REGEX
         .captures_iter(string)
         .map(|cap| {
-            let num = cap[1].parse().unwrap();
+            let num = cap[1]
+                .parse()
+                .map_err(|e| Error::parse(format!("invalid bag count {:?}: {}", &cap[1], e)))?;
             let name = cap[2].trim().to_string();
-            (num, name)
+            Ok((num, name))
         })
         .collect()
 }
@@ -132,7 +155,7 @@ mod unit_tests {
 
     #[test]
     fn test1() {
-        let strings = vec![
+        let strings = [
             "muted lime bags contain 1 wavy lime bag, 1 vibrant green bag, 3 light yellow bags.",
             "light red bags contain 2 clear indigo bags, 3 light lime bags.",
             "wavy beige bags contain 4 faded chartreuse bags.",
@@ -153,7 +176,7 @@ mod unit_tests {
                 (2usize, "clear indigo".to_string()),
                 (3usize, "light lime".to_string())
             ],
-            parse_contained_bags(" 2 clear indigo bags, 3 light lime bags.")
+            parse_contained_bags(" 2 clear indigo bags, 3 light lime bags.").unwrap()
         )
     }
 
@@ -168,6 +191,33 @@ mod unit_tests {
             "dark blue bags contain 2 dark violet bags.".to_string(),
             "dark violet bags contain no other bags.".to_string(),
         ];
-        assert_eq!(126, problem_2(&strings));
+        assert_eq!(
+            Answer::from(126),
+            Day07.part2(&Day07.parse(&strings).unwrap())
+        );
+    }
+
+    #[test]
+    fn no_shiny_gold_bag() {
+        let strings = vec!["dark red bags contain 2 dark orange bags.".to_string()];
+        let graph = Day07.parse(&strings).unwrap();
+        assert_eq!(Answer::Unsolved, Day07.part1(&graph));
+        assert_eq!(Answer::Unsolved, Day07.part2(&graph));
+        let empty = Day07.parse(&[]).unwrap();
+        assert_eq!(Answer::Unsolved, Day07.part1(&empty));
+        assert_eq!(Answer::Unsolved, Day07.part2(&empty));
+    }
+
+    #[test]
+    fn large_counts() {
+        let strings = vec![
+            "shiny gold bags contain 101 dark red bags, 2 dark red bags.".to_string(),
+            "dark red bags contain 1000 dark orange bags.".to_string(),
+            "dark orange bags contain no other bags.".to_string(),
+        ];
+        assert_eq!(
+            Answer::from(103 * 1001),
+            Day07.part2(&Day07.parse(&strings).unwrap())
+        );
     }
 }
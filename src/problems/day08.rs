@@ -1,107 +1,122 @@
-use super::super::utils::read_strings_from_file;
-use itertools::Itertools;
-use std::str::FromStr;
-
-pub fn solve() {
-    let strings = read_strings_from_file("./inputs/day08_1").expect("Failed to read inputs");
-    println!("Problem 1: {:?}", problem_1(&strings));
-    println!("Problem 2: {:?}", problem_2(&strings));
-}
+use super::super::error::Result;
+use super::super::vm::trace::Trace;
+use super::super::vm::{self, cfg, Halt, Instruction, Machine, Program, Registers};
+use super::{Answer, Solution};
+use log::{debug, error, log_enabled, trace, Level};
+use std::convert::TryFrom;
 
-pub fn problem_1(strings: &Vec<String>) -> i32 {
-    let instructions: Vec<Instruction> = strings
-        .iter()
-        .map(|s| s.parse::<Instruction>().unwrap())
-        .collect();
-    println!("{:?}", instructions);
-    let executor = CodeExecutor::new();
-    let result = executor.execute(&instructions);
-    println!("{:?}", result);
-    match result {
-        ExecutionResult::InfiniteLoop(state) => state.accumulator,
-        ExecutionResult::Success(state) => state.accumulator,
+mod debugger;
+mod mutation;
+
+pub use debugger::Debugger;
+use mutation::Mutator;
+pub use mutation::{Mutation, Operator};
+
+pub struct Day08;
+
+impl Solution for Day08 {
+    type Input = Vec<Instruction>;
+
+    fn parse(&self, strings: &[String]) -> Result<Self::Input> {
+        let instructions = vm::assemble(strings)?;
+        debug!("{:?}", instructions);
+        Ok(instructions)
     }
-}
 
-pub fn problem_2(strings: &Vec<String>) -> Option<i32> {
-    let instructions: Vec<Instruction> = strings
-        .iter()
-        .map(|s| s.parse::<Instruction>().unwrap())
-        .collect();
-    println!("{:?}", instructions);
-    let code_generator = CodeCandidateGenerator{ instructions, index: 0 };
-    let executor = CodeExecutor::new();
-    for code_candidate in code_generator {
-        println!("Candidate: {:?}", code_candidate);
-        let result = executor.execute(&code_candidate);
-        println!("{:?}", result);
+    fn part1(&self, instructions: &Self::Input) -> Answer {
+        let executor = CodeExecutor::new();
+        let result = if log_enabled!(Level::Trace) {
+            let (result, trace) = executor.execute_traced(instructions);
+            trace!("\n{}", trace.to_text());
+            result
+        } else {
+            executor.execute(instructions)
+        };
+        debug!("{:?}", result);
         match result {
-            ExecutionResult::Success(state) => { return Some(state.accumulator); },
-            ExecutionResult::InfiniteLoop(state) => { continue; },
+            ExecutionResult::InfiniteLoop(state) => state.accumulator.into(),
+            ExecutionResult::Success(state) => state.accumulator.into(),
+            _ => Answer::Unsolved,
         }
     }
-    None
+
+    fn part2(&self, instructions: &Self::Input) -> Answer {
+        let repair = match cfg::repair(instructions) {
+            Some(repair) => repair,
+            None => return Answer::Unsolved,
+        };
+        debug!(
+            "flipped {} at {}",
+            instructions[repair.address], repair.address
+        );
+        let accumulator = match CodeExecutor::new().execute(&repair.program) {
+            ExecutionResult::Success(state) => state.accumulator,
+            result => {
+                error!("repaired program did not terminate: {:?}", result);
+                return Answer::Unsolved;
+            }
+        };
+        accumulator.into()
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-struct Instruction {
-    operation: String,
-    argument: i32,
+/// Runs `program` and records everything it executes, for the `trace` command.
+pub fn trace_program(program: &[Instruction]) -> Trace {
+    CodeExecutor::new().execute_traced(program).1
 }
 
-impl FromStr for Instruction {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Option<(&str, &str)> = s.split(' ').map(|s| s.trim()).collect_tuple();
-        if let Some((a, b)) = parts {
-            let operation = a.trim().to_string();
-            let argument = b.parse::<i32>().unwrap();
-            Ok(Instruction {
-                operation,
-                argument,
-            })
-        } else {
-            Err("Can not parse string".to_string())
+/// The fewest edits made by `operators`, at most `max_edits` of them, after
+/// which `program` terminates, with the final accumulator. For the `repair`
+/// command, which goes beyond part 2's single flip.
+pub fn search_repair(
+    program: &[Instruction],
+    operators: Vec<Operator>,
+    max_edits: usize,
+) -> Option<(Vec<Mutation>, i64)> {
+    let (candidate, result) = Mutator::new(operators, max_edits)
+        .search(program, |r| matches!(r, ExecutionResult::Success(_)))?;
+    match result {
+        ExecutionResult::Success(state) => {
+            Some((candidate.mutations().to_vec(), state.accumulator))
         }
+        _ => None,
     }
 }
 
-impl Instruction {
-    fn execute(&self, state: &ExecutionState) -> ExecutionState {
-        match &self.operation[..] {
-            "acc" => ExecutionState {
-                accumulator: state.accumulator + self.argument,
-                address: state.address + 1,
-            },
-            "jmp" => ExecutionState {
-                accumulator: state.accumulator,
-                address: state.address + self.argument,
-            },
-            "nop" => ExecutionState {
-                accumulator: state.accumulator,
-                address: state.address + 1,
-            },
-            _ => ExecutionState {
-                accumulator: state.accumulator,
-                address: state.address,
-            },
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct ExecutionState {
+    accumulator: i64,
+    address: i64,
+}
+
+impl ExecutionState {
+    fn registers(&self) -> Registers {
+        Registers {
+            accumulator: self.accumulator,
+            address: self.address,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct ExecutionState {
-    accumulator: i32,
-    address: i32,
+impl From<Registers> for ExecutionState {
+    fn from(registers: Registers) -> Self {
+        Self {
+            accumulator: registers.accumulator,
+            address: registers.address,
+        }
+    }
 }
 
-impl ExecutionState {}
-
 #[derive(Debug, PartialEq)]
 enum ExecutionResult {
     Success(ExecutionState),
     InfiniteLoop(ExecutionState),
+    /// A jump landed on a negative address.
+    JumpBelowZero(ExecutionState),
+    /// A jump landed more than one instruction past the end.
+    JumpPastEnd(ExecutionState),
+    /// There was nothing to execute.
+    EmptyProgram,
 }
 
 #[derive(Debug, PartialEq)]
@@ -112,87 +127,270 @@ impl CodeExecutor {
         Self {}
     }
 
-    fn execute(&self, instructions: &Vec<Instruction>) -> ExecutionResult {
-        let mut state = ExecutionState {
-            accumulator: 0,
-            address: 0,
-        };
-        let mut loop_tracker = vec![false; instructions.len()];
-        loop {
-            let new_state = instructions[state.address as usize].execute(&state);
-            if new_state.address as usize >= instructions.len() {
-                return ExecutionResult::Success(new_state);
-            } else if loop_tracker[new_state.address as usize] {
-                return ExecutionResult::InfiniteLoop(state);
-            } else  {
-                loop_tracker[new_state.address as usize] = true;
-                state = new_state;
-            }
-        }
+    /// Executes the instruction at `state.address`, `None` if there is none.
+    fn step(&self, instructions: &[Instruction], state: &ExecutionState) -> Option<ExecutionState> {
+        let instruction = usize::try_from(state.address)
+            .ok()
+            .and_then(|a| instructions.get(a))?;
+        Some(instruction.execute(state.registers()).into())
     }
-}
 
-struct CodeCandidateGenerator {
-    instructions: Vec<Instruction>,
-    index: usize,
-}
+    fn execute<P: Program + ?Sized>(&self, program: &P) -> ExecutionResult {
+        let mut machine = Machine::new(program);
+        let halt = machine.run();
+        self.result(program, halt, machine.registers())
+    }
 
-impl Iterator for CodeCandidateGenerator {
-    type Item = Vec<Instruction>;
-    fn next(&mut self) -> Option<Self::Item> {
-        while let Some(instruction) = self.instructions.get(self.index) {
-            match &instruction.operation[..] {
-                "acc" => {
-                    self.index += 1;
-                },
-                _ => {
-                    // jmp or nop instruction
-                    let mut mutated_code: Vec<Instruction> = self.instructions.iter().cloned().collect();
-                    mutated_code[self.index] = if instruction.operation == "jmp" {
-                        Instruction{operation: "nop".to_string(), argument: instruction.argument }
-                    } else {
-                        Instruction{operation: "jmp".to_string(), argument: instruction.argument }
-                    };
-                    self.index += 1;
-                    return Some(mutated_code);
-                }
-            }
+    /// Like `execute`, also recording every instruction executed on the way.
+    fn execute_traced(&self, instructions: &[Instruction]) -> (ExecutionResult, Trace) {
+        let mut machine = Machine::new(instructions);
+        let trace = Trace::record(&mut machine);
+        (
+            self.result(instructions, trace.halt, machine.registers()),
+            trace,
+        )
+    }
+
+    fn result<P: Program + ?Sized>(
+        &self,
+        program: &P,
+        halt: Halt,
+        registers: Registers,
+    ) -> ExecutionResult {
+        if program.is_empty() {
+            return ExecutionResult::EmptyProgram;
+        }
+        let state = ExecutionState::from(registers);
+        match halt {
+            Halt::Terminated => ExecutionResult::Success(state),
+            Halt::InfiniteLoop => ExecutionResult::InfiniteLoop(state),
+            Halt::JumpOutOfBounds if state.address < 0 => ExecutionResult::JumpBelowZero(state),
+            Halt::JumpOutOfBounds => ExecutionResult::JumpPastEnd(state),
+            Halt::StepLimit => unreachable!("run() has no step limit"),
         }
-        None
     }
 }
+
 #[cfg(test)]
 mod tests {
+    use super::super::super::vm::analysis::Analysis;
+    use super::super::super::vm::fixtures::{self, example, program, strings};
+    use super::super::super::vm::Opcode;
+    use super::mutation::{Mutation, Mutator, Operator};
     use super::*;
 
     #[test]
     fn test1() {
-        let strings: Vec<String> = vec![
-            "nop +0", "acc +1", "jmp +4", "acc +3", "jmp -3", "acc -99", "acc +1", "jmp -4",
-            "acc +6",
-        ]
-            .iter()
-            .map(|s| s.to_string())
-            .collect();
-        assert_eq!(5, problem_1(&strings));
+        assert_eq!(
+            Answer::from(5),
+            Day08.part1(&Day08.parse(&strings(&fixtures::EXAMPLE)).unwrap())
+        );
     }
 
     #[test]
     fn test2() {
-        let strings: Vec<String> = vec![
-            "nop +0",
-            "acc +1",
-            "jmp +4",
-            "acc +3",
-            "jmp -3",
-            "acc -99",
-            "acc +1",
-            "jmp -4",
-            "acc +6",
-        ]
-            .iter()
-            .map(|s| s.to_string())
+        assert_eq!(
+            Answer::from(8),
+            Day08.part2(&Day08.parse(&strings(&fixtures::EXAMPLE)).unwrap())
+        );
+    }
+
+    #[test]
+    fn malformed_argument() {
+        let e = Day08.parse(&strings(&["nop +0", "acc +x"])).unwrap_err();
+        assert_eq!("line 2:5: invalid argument \"+x\"", e.to_string());
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let e = Day08
+            .parse(&strings(&["nop +0", "acc +1", "hlt +0"]))
+            .unwrap_err();
+        assert_eq!("line 3:1: unknown opcode \"hlt\"", e.to_string());
+    }
+
+    #[test]
+    fn jump_below_zero() {
+        let result = CodeExecutor::new().execute(&program(&["acc +1", "jmp -2", "acc +5"]));
+        assert_eq!(
+            ExecutionResult::JumpBelowZero(ExecutionState {
+                accumulator: 1,
+                address: -1
+            }),
+            result
+        );
+    }
+
+    #[test]
+    fn jump_past_end() {
+        let result = CodeExecutor::new().execute(&program(&["acc +2", "jmp +3", "acc +5"]));
+        assert_eq!(
+            ExecutionResult::JumpPastEnd(ExecutionState {
+                accumulator: 2,
+                address: 4
+            }),
+            result
+        );
+        // landing exactly one past the end is a normal termination
+        let result = CodeExecutor::new().execute(&program(&["acc +2", "jmp +2", "acc +5"]));
+        assert_eq!(
+            ExecutionResult::Success(ExecutionState {
+                accumulator: 2,
+                address: 3
+            }),
+            result
+        );
+    }
+
+    #[test]
+    fn empty_program() {
+        assert_eq!(
+            ExecutionResult::EmptyProgram,
+            CodeExecutor::new().execute::<[Instruction]>(&[])
+        );
+        assert_eq!(Answer::Unsolved, Day08.part1(&vec![]));
+        assert_eq!(Answer::Unsolved, Day08.part2(&vec![]));
+    }
+
+    #[test]
+    fn repair_agrees_with_brute_force() {
+        let program = example();
+        let executor = CodeExecutor::new();
+        let terminating: Vec<Vec<Instruction>> = Mutator::new(vec![Operator::Flip], 1)
+            .candidates(&program)
+            .filter(|candidate| matches!(executor.execute(candidate), ExecutionResult::Success(_)))
+            .map(|candidate| candidate.to_program())
             .collect();
-        assert_eq!(Some(8), problem_2(&strings));
+        assert_eq!(vec![cfg::repair(&program).unwrap().program], terminating);
+    }
+
+    #[test]
+    fn traced_execution_matches() {
+        let program = example();
+        let (result, trace) = CodeExecutor::new().execute_traced(&program);
+        assert_eq!(CodeExecutor::new().execute(&program), result);
+        assert_eq!(Some(1), trace.loop_entry);
+        assert_eq!(6, trace.loop_body().unwrap().len());
+        assert_eq!(
+            ExecutionResult::EmptyProgram,
+            CodeExecutor::new().execute_traced(&[]).0
+        );
+    }
+
+    /// Small programs with short jumps, some of them out of bounds.
+    fn random_programs(count: usize) -> Vec<Vec<Instruction>> {
+        let mut seed = 0x2020_0808u64;
+        let mut random = move |n: u64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed % n
+        };
+        (0..count)
+            .map(|_| {
+                let len = 1 + random(10);
+                (0..len)
+                    .map(|_| match random(3) {
+                        0 => Instruction::new(Opcode::Acc, random(19) as i32 - 9),
+                        1 => Instruction::new(Opcode::Jmp, random(7) as i32 - 3),
+                        _ => Instruction::new(Opcode::Nop, random(7) as i32 - 3),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Runs `program` from `address` rather than 0.
+    fn run_from(program: &[Instruction], address: usize) -> ExecutionResult {
+        let executor = CodeExecutor::new();
+        let mut state = ExecutionState {
+            accumulator: 0,
+            address: address as i64,
+        };
+        let mut executed = vec![false; program.len()];
+        while let Some(next) = executor.step(program, &state) {
+            if executed[state.address as usize] {
+                return ExecutionResult::InfiniteLoop(state);
+            }
+            executed[state.address as usize] = true;
+            state = next;
+        }
+        let halt = if state.address == program.len() as i64 {
+            Halt::Terminated
+        } else {
+            Halt::JumpOutOfBounds
+        };
+        executor.result(program, halt, state.registers())
+    }
+
+    #[test]
+    fn analysis_agrees_with_execution() {
+        let executor = CodeExecutor::new();
+        for program in random_programs(500) {
+            let analysis = Analysis::new(&program);
+            for address in 0..=program.len() {
+                let fate = analysis.fate(address);
+                assert_eq!(
+                    run_from(&program, address),
+                    executor.result(&program, fate.halt, fate.registers),
+                    "{:?} from {}",
+                    program,
+                    address
+                );
+            }
+            for candidate in Mutator::new(vec![Operator::Flip], 1).candidates(&program) {
+                let address = match candidate.mutations()[0] {
+                    Mutation::Replace { address, .. } => address,
+                    _ => unreachable!(),
+                };
+                let fate = analysis.flipped(address).unwrap();
+                assert_eq!(
+                    executor.execute(&candidate),
+                    executor.result(&program, fate.halt, fate.registers),
+                    "{:?} flipped at {}",
+                    program,
+                    address
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn intervals_cover_every_run() {
+        let executor = CodeExecutor::new();
+        for (n, program) in random_programs(500).into_iter().enumerate() {
+            let analysis = Analysis::new(&program);
+            // a different set of flips for every program
+            let flipped: Vec<Instruction> = program
+                .iter()
+                .enumerate()
+                .map(|(a, i)| match i.opcode.flipped() {
+                    Some(opcode) if (n >> (a % 8)) & 1 == 1 => Instruction::new(opcode, i.argument),
+                    _ => *i,
+                })
+                .collect();
+            let (result, trace) = executor.execute_traced(&flipped);
+            for entry in &trace.entries {
+                let before = entry.accumulator - entry.accumulator_delta;
+                assert!(
+                    analysis
+                        .accumulator(entry.address)
+                        .unwrap()
+                        .contains(before),
+                    "{:?}",
+                    flipped
+                );
+            }
+            if let ExecutionResult::Success(state) = result {
+                assert!(
+                    analysis
+                        .accumulator(program.len())
+                        .unwrap()
+                        .contains(state.accumulator),
+                    "{:?}",
+                    flipped
+                );
+            }
+        }
     }
 }
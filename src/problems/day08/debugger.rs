@@ -0,0 +1,576 @@
+//! A step debugger for console programs, driven by one command per line so a
+//! session can be typed at a prompt or replayed from a file.
+
+use super::super::super::vm::Instruction;
+use super::{CodeExecutor, ExecutionState};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+step [n]           (s)  execute the next n instructions
+reverse [n]        (rs) undo the last n instructions
+continue           (c)  run until a breakpoint, a repeated instruction or the end
+break addr|acc N   (b)  stop when the address or the accumulator becomes N
+delete N                remove breakpoint N
+watch EXPR              show EXPR after every stop: addr, acc, instr, visits (of the
+                        current address), visits[N], a number, or two of them
+                        compared with == != < <= > >=, e.g. `watch acc >= 100`
+unwatch EXPR            stop showing EXPR
+info                    list breakpoints and watches
+print              (p)  show the current state
+quit               (q)  leave the debugger";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Breakpoint {
+    Address(i64),
+    Accumulator(i64),
+}
+
+impl Breakpoint {
+    fn is_hit(&self, state: &ExecutionState) -> bool {
+        match *self {
+            Breakpoint::Address(address) => state.address == address,
+            Breakpoint::Accumulator(value) => state.accumulator == value,
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Address(address) => write!(f, "address {}", address),
+            Breakpoint::Accumulator(value) => write!(f, "accumulator {}", value),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Operand {
+    Address,
+    Accumulator,
+    Instruction,
+    /// Executions of the current address.
+    Visits,
+    VisitsAt(usize),
+    Number(i64),
+}
+
+impl Operand {
+    /// `len` is the length of the program, which `visits[N]` has to stay within.
+    fn parse(s: &str, len: usize) -> Result<Self, String> {
+        let operand = match s {
+            "addr" => Operand::Address,
+            "acc" => Operand::Accumulator,
+            "instr" => Operand::Instruction,
+            "visits" => Operand::Visits,
+            _ => match s.strip_prefix("visits[").and_then(|s| s.strip_suffix(']')) {
+                Some(n) => match n.parse::<usize>() {
+                    Ok(n) if n < len => Operand::VisitsAt(n),
+                    _ => return Err(format!("no address {:?} in the program", n)),
+                },
+                None => match s.parse::<i64>() {
+                    Ok(n) => Operand::Number(n),
+                    Err(_) => return Err(format!("cannot watch {:?}", s)),
+                },
+            },
+        };
+        Ok(operand)
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Address => write!(f, "addr"),
+            Operand::Accumulator => write!(f, "acc"),
+            Operand::Instruction => write!(f, "instr"),
+            Operand::Visits => write!(f, "visits"),
+            Operand::VisitsAt(address) => write!(f, "visits[{}]", address),
+            Operand::Number(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+/// Longer operators first, so `<=` is not read as `<`.
+const COMPARISONS: [&str; 6] = ["==", "!=", "<=", ">=", "<", ">"];
+
+/// A watch expression: a value, or two numbers compared.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Watch {
+    left: Operand,
+    comparison: Option<(&'static str, Operand)>,
+}
+
+impl Watch {
+    fn parse(s: &str, len: usize) -> Result<Self, String> {
+        let split = COMPARISONS
+            .iter()
+            .find_map(|&op| s.find(op).map(|i| (op, i)));
+        let watch = match split {
+            None => Watch {
+                left: Operand::parse(s.trim(), len)?,
+                comparison: None,
+            },
+            Some((op, i)) => {
+                let left = Operand::parse(s[..i].trim(), len)?;
+                let right = Operand::parse(s[i + op.len()..].trim(), len)?;
+                if left == Operand::Instruction || right == Operand::Instruction {
+                    return Err("instr can only be watched on its own".to_string());
+                }
+                Watch {
+                    left,
+                    comparison: Some((op, right)),
+                }
+            }
+        };
+        Ok(watch)
+    }
+}
+
+impl fmt::Display for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.comparison {
+            None => write!(f, "{}", self.left),
+            Some((op, right)) => write!(f, "{} {} {}", self.left, op, right),
+        }
+    }
+}
+
+pub struct Debugger<'a> {
+    program: &'a [Instruction],
+    executor: CodeExecutor,
+    state: ExecutionState,
+    /// The state before every instruction executed so far, for reverse steps.
+    history: Vec<ExecutionState>,
+    /// How often each address has been executed.
+    visits: Vec<u32>,
+    breakpoints: Vec<Breakpoint>,
+    watches: Vec<Watch>,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(program: &'a [Instruction]) -> Self {
+        Self {
+            program,
+            executor: CodeExecutor::new(),
+            state: ExecutionState {
+                accumulator: 0,
+                address: 0,
+            },
+            history: Vec::new(),
+            visits: vec![0; program.len()],
+            breakpoints: Vec::new(),
+            watches: Vec::new(),
+        }
+    }
+
+    fn current(&self) -> Option<&Instruction> {
+        usize::try_from(self.state.address)
+            .ok()
+            .and_then(|a| self.program.get(a))
+    }
+
+    /// Why there is no instruction to execute at the current address.
+    fn halt_reason(&self) -> &'static str {
+        let address = self.state.address;
+        if address < 0 {
+            "jumped below zero"
+        } else if address as usize > self.program.len() {
+            "jumped past the end"
+        } else {
+            "program terminated"
+        }
+    }
+
+    fn describe(&self) -> String {
+        let location = format!(
+            "address {}, accumulator {}",
+            self.state.address, self.state.accumulator
+        );
+        match self.current() {
+            Some(instruction) => format!("{}: {}", location, instruction),
+            None => format!("{}: {}", location, self.halt_reason()),
+        }
+    }
+
+    /// The value of a numeric operand; `instr` has none.
+    fn number(&self, operand: Operand) -> Option<i64> {
+        match operand {
+            Operand::Address => Some(self.state.address),
+            Operand::Accumulator => Some(self.state.accumulator),
+            Operand::Instruction => None,
+            Operand::Visits => Some(
+                self.current()
+                    .map_or(0, |_| self.visits[self.state.address as usize])
+                    .into(),
+            ),
+            Operand::VisitsAt(address) => Some(self.visits[address].into()),
+            Operand::Number(n) => Some(n),
+        }
+    }
+
+    fn watch_value(&self, watch: Watch) -> String {
+        let left = self.number(watch.left);
+        match (watch.comparison, left) {
+            (None, Some(value)) => value.to_string(),
+            (None, None) => self.current().map_or("-".to_string(), |i| i.to_string()),
+            (Some((op, right)), Some(left)) => {
+                let right = self.number(right).expect("parse only compares numbers");
+                let holds = match op {
+                    "==" => left == right,
+                    "!=" => left != right,
+                    "<=" => left <= right,
+                    ">=" => left >= right,
+                    "<" => left < right,
+                    _ => left > right,
+                };
+                holds.to_string()
+            }
+            (Some(_), None) => unreachable!("parse only compares numbers"),
+        }
+    }
+
+    fn step_once(&mut self) -> bool {
+        match self.executor.step(self.program, &self.state) {
+            Some(next) => {
+                self.visits[self.state.address as usize] += 1;
+                self.history.push(self.state);
+                self.state = next;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn reverse_once(&mut self) -> bool {
+        match self.history.pop() {
+            Some(previous) => {
+                self.visits[previous.address as usize] -= 1;
+                self.state = previous;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn looping(&self) -> Option<String> {
+        if self.current().is_some() && self.visits[self.state.address as usize] > 0 {
+            Some(format!(
+                "infinite loop: address {} would run a second time",
+                self.state.address
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Runs at least one instruction, then until something worth stopping for.
+    fn resume(&mut self) -> String {
+        loop {
+            if !self.step_once() {
+                return self.halt_reason().to_string();
+            }
+            if let Some((i, b)) = self
+                .breakpoints
+                .iter()
+                .enumerate()
+                .find(|(_, b)| b.is_hit(&self.state))
+            {
+                return format!("breakpoint {} ({}) hit", i + 1, b);
+            }
+            if let Some(reason) = self.looping() {
+                return reason;
+            }
+        }
+    }
+
+    /// Runs up to `n` instructions, stopping early like `resume` at the end or
+    /// in front of a loop, so a large `n` cannot fill the history.
+    fn step(&mut self, n: usize) -> Option<String> {
+        for k in 1..=n {
+            if !self.step_once() {
+                return Some(self.halt_reason().to_string());
+            }
+            if k < n {
+                if let Some(reason) = self.looping() {
+                    return Some(reason);
+                }
+            }
+        }
+        None
+    }
+
+    fn show_state<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{}", self.describe())?;
+        for &watch in &self.watches {
+            writeln!(out, "  {} = {}", watch, self.watch_value(watch))?;
+        }
+        Ok(())
+    }
+
+    /// Executes one command line, returns `false` once the session is over.
+    pub fn command<W: Write>(&mut self, line: &str, out: &mut W) -> io::Result<bool> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let count = |words: &[&str]| words.get(1).map_or(Some(1), |n| n.parse::<usize>().ok());
+        match words.as_slice() {
+            [] => {}
+            [comment, ..] if comment.starts_with('#') => {}
+            ["step", ..] | ["s", ..] => match count(&words) {
+                Some(n) => {
+                    if let Some(reason) = self.step(n) {
+                        writeln!(out, "{}", reason)?;
+                    }
+                    self.show_state(out)?;
+                }
+                None => writeln!(out, "expected a number of steps, got {:?}", words[1])?,
+            },
+            ["reverse", ..] | ["rs", ..] => match count(&words) {
+                Some(n) => {
+                    if !(0..n).all(|_| self.reverse_once()) {
+                        writeln!(out, "at the start of the program")?;
+                    }
+                    self.show_state(out)?;
+                }
+                None => writeln!(out, "expected a number of steps, got {:?}", words[1])?,
+            },
+            ["continue"] | ["c"] => {
+                let reason = self.resume();
+                writeln!(out, "{}", reason)?;
+                self.show_state(out)?;
+            }
+            ["break", kind, value] | ["b", kind, value] => match (*kind, value.parse::<i64>()) {
+                ("addr", Ok(n)) => self.add_breakpoint(Breakpoint::Address(n), out)?,
+                ("acc", Ok(n)) => self.add_breakpoint(Breakpoint::Accumulator(n), out)?,
+                _ => writeln!(out, "usage: break addr|acc N")?,
+            },
+            ["delete", n] => match n.parse::<usize>() {
+                Ok(n) if (1..=self.breakpoints.len()).contains(&n) => {
+                    let b = self.breakpoints.remove(n - 1);
+                    writeln!(out, "deleted breakpoint {} ({})", n, b)?;
+                }
+                _ => writeln!(out, "no breakpoint {}", n)?,
+            },
+            ["watch", ..] | ["unwatch", ..] => {
+                let expression = line.trim()[words[0].len()..].trim();
+                match Watch::parse(expression, self.program.len()) {
+                    Ok(watch) if words[0] == "watch" => {
+                        if !self.watches.contains(&watch) {
+                            self.watches.push(watch);
+                        }
+                        writeln!(out, "  {} = {}", watch, self.watch_value(watch))?;
+                    }
+                    Ok(watch) => self.watches.retain(|&w| w != watch),
+                    Err(message) => writeln!(out, "{}, try help", message)?,
+                }
+            }
+            ["info"] => {
+                for (i, b) in self.breakpoints.iter().enumerate() {
+                    writeln!(out, "breakpoint {}: {}", i + 1, b)?;
+                }
+                for watch in &self.watches {
+                    writeln!(out, "watch {}", watch)?;
+                }
+            }
+            ["print"] | ["p"] => self.show_state(out)?,
+            ["help"] | ["h"] => writeln!(out, "{}", HELP)?,
+            ["quit"] | ["q"] => return Ok(false),
+            _ => writeln!(out, "unknown command {:?}, try help", line.trim())?,
+        }
+        Ok(true)
+    }
+
+    fn add_breakpoint<W: Write>(&mut self, breakpoint: Breakpoint, out: &mut W) -> io::Result<()> {
+        self.breakpoints.push(breakpoint);
+        writeln!(out, "breakpoint {}: {}", self.breakpoints.len(), breakpoint)
+    }
+
+    /// Reads commands until `quit` or the end of `input`. A prompt is shown for
+    /// interactive sessions, scripted ones echo each command instead.
+    pub fn run<R: BufRead, W: Write>(
+        &mut self,
+        input: R,
+        out: &mut W,
+        interactive: bool,
+    ) -> io::Result<()> {
+        self.show_state(out)?;
+        let mut lines = input.lines();
+        loop {
+            if interactive {
+                write!(out, "(dbg) ")?;
+                out.flush()?;
+            }
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(()),
+            };
+            if !interactive {
+                writeln!(out, "(dbg) {}", line)?;
+            }
+            if !self.command(&line, out)? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::super::vm::fixtures::{example, program};
+    use super::*;
+
+    fn session(script: &str) -> String {
+        let program = example();
+        let mut out = Vec::new();
+        Debugger::new(&program)
+            .run(script.as_bytes(), &mut out, false)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn step_and_reverse() {
+        let transcript = session("step 3\nreverse\nreverse 5\nquit\nstep\n");
+        assert_eq!(
+            "address 0, accumulator 0: nop +0
+(dbg) step 3
+address 6, accumulator 1: acc +1
+(dbg) reverse
+address 2, accumulator 1: jmp +4
+(dbg) reverse 5
+at the start of the program
+address 0, accumulator 0: nop +0
+(dbg) quit
+",
+            transcript
+        );
+    }
+
+    #[test]
+    fn continue_to_breakpoints_and_loops() {
+        let transcript = session(
+            "# stop on the accumulator first
+break acc 2
+watch instr
+c
+delete 1
+break addr 4
+info
+c
+c
+",
+        );
+        assert_eq!(
+            "address 0, accumulator 0: nop +0
+(dbg) # stop on the accumulator first
+(dbg) break acc 2
+breakpoint 1: accumulator 2
+(dbg) watch instr
+  instr = nop +0
+(dbg) c
+breakpoint 1 (accumulator 2) hit
+address 7, accumulator 2: jmp -4
+  instr = jmp -4
+(dbg) delete 1
+deleted breakpoint 1 (accumulator 2)
+(dbg) break addr 4
+breakpoint 1: address 4
+(dbg) info
+breakpoint 1: address 4
+watch instr
+(dbg) c
+breakpoint 1 (address 4) hit
+address 4, accumulator 5: jmp -3
+  instr = jmp -3
+(dbg) c
+infinite loop: address 1 would run a second time
+address 1, accumulator 5: acc +1
+  instr = acc +1
+",
+            transcript
+        );
+    }
+
+    #[test]
+    fn halting_and_mistakes() {
+        let program = program(&["acc +1", "jmp +2"]);
+        let mut debugger = Debugger::new(&program);
+        let mut out = Vec::new();
+        for line in &["frobnicate", "step x", "watch pc", "c", "step"] {
+            debugger.command(line, &mut out).unwrap();
+        }
+        assert_eq!(
+            "unknown command \"frobnicate\", try help
+expected a number of steps, got \"x\"
+cannot watch \"pc\", try help
+jumped past the end
+address 3, accumulator 1: jumped past the end
+jumped past the end
+address 3, accumulator 1: jumped past the end
+",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn watch_expressions() {
+        let transcript = session(
+            "watch acc>=5
+watch visits[1] == 1
+watch acc >= 5
+watch instr < 3
+watch visits[9]
+c
+unwatch acc >= 5
+p
+",
+        );
+        assert_eq!(
+            "address 0, accumulator 0: nop +0
+(dbg) watch acc>=5
+  acc >= 5 = false
+(dbg) watch visits[1] == 1
+  visits[1] == 1 = false
+(dbg) watch acc >= 5
+  acc >= 5 = false
+(dbg) watch instr < 3
+instr can only be watched on its own, try help
+(dbg) watch visits[9]
+no address \"9\" in the program, try help
+(dbg) c
+infinite loop: address 1 would run a second time
+address 1, accumulator 5: acc +1
+  acc >= 5 = true
+  visits[1] == 1 = true
+(dbg) unwatch acc >= 5
+(dbg) p
+address 1, accumulator 5: acc +1
+  visits[1] == 1 = true
+",
+            transcript
+        );
+    }
+
+    #[test]
+    fn steps_stop_in_front_of_a_loop() {
+        let transcript = session(
+            "step 1000000000
+step
+step 2
+",
+        );
+        assert_eq!(
+            "address 0, accumulator 0: nop +0
+(dbg) step 1000000000
+infinite loop: address 1 would run a second time
+address 1, accumulator 5: acc +1
+(dbg) step
+address 2, accumulator 6: jmp +4
+(dbg) step 2
+infinite loop: address 6 would run a second time
+address 6, accumulator 6: acc +1
+",
+            transcript
+        );
+    }
+}
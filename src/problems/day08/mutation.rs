@@ -0,0 +1,412 @@
+//! Searching for programs a few edits away from a given one. Candidates are
+//! views of the original program with a short list of edits on top, so
+//! trying one costs no more than the edits themselves.
+
+use super::super::super::error::{Error, Result};
+use super::super::super::vm::{Instruction, Opcode, Program};
+use super::{CodeExecutor, ExecutionResult};
+use itertools::Itertools;
+use std::fmt;
+use std::str::FromStr;
+
+const OPCODES: [Opcode; 3] = [Opcode::Acc, Opcode::Jmp, Opcode::Nop];
+
+/// Largest `adjust=K` accepted: every address gets `2K` adjustments, all of
+/// them listed before the search starts.
+pub const MAX_ADJUSTMENT: i32 = 1000;
+
+/// One edit, addressed by the position in the original program.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Mutation {
+    Replace {
+        address: usize,
+        opcode: Opcode,
+    },
+    Adjust {
+        address: usize,
+        delta: i32,
+    },
+    Delete {
+        address: usize,
+    },
+    /// Inserts before the original `address`, or appends for `address == len`.
+    Insert {
+        address: usize,
+        instruction: Instruction,
+    },
+}
+
+impl Mutation {
+    fn address(&self) -> usize {
+        match *self {
+            Mutation::Replace { address, .. }
+            | Mutation::Adjust { address, .. }
+            | Mutation::Delete { address }
+            | Mutation::Insert { address, .. } => address,
+        }
+    }
+}
+
+impl fmt::Display for Mutation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mutation::Replace { address, opcode } => {
+                write!(f, "change the opcode at {} to {}", address, opcode)
+            }
+            Mutation::Adjust { address, delta } => {
+                write!(f, "add {:+} to the argument at {}", delta, address)
+            }
+            Mutation::Delete { address } => write!(f, "delete the instruction at {}", address),
+            Mutation::Insert {
+                address,
+                instruction,
+            } => write!(f, "insert {} before {}", instruction, address),
+        }
+    }
+}
+
+/// Kinds of edit a `Mutator` tries at every address.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operator {
+    /// Day 8's corruption: `jmp` to `nop` and back.
+    Flip,
+    /// Any opcode to any other opcode.
+    SwapOpcodes,
+    /// Arguments changed by up to ±k, at most `MAX_ADJUSTMENT`.
+    Adjust(i32),
+    Delete,
+    /// This instruction inserted anywhere, the end included.
+    Insert(Instruction),
+}
+
+/// Parses `flip`, `swap`, `adjust=K`, `delete` or `insert=INSTRUCTION`.
+impl FromStr for Operator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once('=') {
+            None if s == "flip" => Ok(Operator::Flip),
+            None if s == "swap" => Ok(Operator::SwapOpcodes),
+            None if s == "delete" => Ok(Operator::Delete),
+            Some(("adjust", k)) => match k.parse::<i32>() {
+                Ok(k) if (1..=MAX_ADJUSTMENT).contains(&k) => Ok(Operator::Adjust(k)),
+                _ => Err(Error::parse(format!(
+                    "expected an adjustment from 1 to {}, got {:?}",
+                    MAX_ADJUSTMENT, k
+                ))),
+            },
+            Some(("insert", instruction)) => Ok(Operator::Insert(instruction.parse()?)),
+            _ => Err(Error::parse(format!(
+                "unknown edit {:?}, expected flip, swap, adjust=K, delete or insert=INSTRUCTION",
+                s
+            ))),
+        }
+    }
+}
+
+/// `base` with `mutations` applied, at distinct addresses and sorted by them.
+#[derive(Debug, Clone)]
+pub struct Candidate<'a> {
+    base: &'a [Instruction],
+    mutations: Vec<Mutation>,
+}
+
+impl<'a> Candidate<'a> {
+    pub fn mutations(&self) -> &[Mutation] {
+        &self.mutations
+    }
+
+    #[cfg(test)]
+    pub fn to_program(&self) -> Vec<Instruction> {
+        (0..self.len()).map(|a| self.fetch(a).unwrap()).collect()
+    }
+}
+
+impl<'a> Program for Candidate<'a> {
+    fn len(&self) -> usize {
+        self.mutations
+            .iter()
+            .fold(self.base.len(), |len, m| match m {
+                Mutation::Delete { .. } => len - 1,
+                Mutation::Insert { .. } => len + 1,
+                _ => len,
+            })
+    }
+
+    fn fetch(&self, address: usize) -> Option<Instruction> {
+        // `shift` is how far instructions from the original have moved so far
+        let mut shift = 0isize;
+        for mutation in &self.mutations {
+            let here = (mutation.address() as isize + shift) as usize;
+            if address < here {
+                break;
+            }
+            match *mutation {
+                Mutation::Insert { instruction, .. } if address == here => {
+                    return Some(instruction)
+                }
+                Mutation::Insert { .. } => shift += 1,
+                Mutation::Delete { .. } => shift -= 1,
+                Mutation::Replace { address: a, opcode } if address == here => {
+                    return Some(Instruction::new(opcode, self.base[a].argument))
+                }
+                Mutation::Adjust { address: a, delta } if address == here => {
+                    // `Mutator` only proposes adjustments that fit an `i32`
+                    let original = self.base[a];
+                    return Some(Instruction::new(
+                        original.opcode,
+                        original.argument.checked_add(delta)?,
+                    ));
+                }
+                _ => {}
+            }
+        }
+        self.base.get((address as isize - shift) as usize).copied()
+    }
+}
+
+pub struct Mutator {
+    operators: Vec<Operator>,
+    max_mutations: usize,
+}
+
+impl Mutator {
+    /// Tries every combination of up to `max_mutations` edits made by `operators`.
+    pub fn new(operators: Vec<Operator>, max_mutations: usize) -> Self {
+        Self {
+            operators,
+            max_mutations,
+        }
+    }
+
+    /// Every single edit the operators can make to `program`, by address.
+    fn mutations(&self, program: &[Instruction]) -> Vec<Mutation> {
+        let mut mutations = Vec::new();
+        for address in 0..=program.len() {
+            for operator in &self.operators {
+                match (operator, program.get(address)) {
+                    (Operator::Insert(instruction), _) => mutations.push(Mutation::Insert {
+                        address,
+                        instruction: *instruction,
+                    }),
+                    (_, None) => {}
+                    (Operator::Flip, Some(i)) => {
+                        if let Some(opcode) = i.opcode.flipped() {
+                            mutations.push(Mutation::Replace { address, opcode });
+                        }
+                    }
+                    (Operator::SwapOpcodes, Some(i)) => mutations.extend(
+                        OPCODES
+                            .iter()
+                            .filter(|&&o| o != i.opcode)
+                            .map(|&opcode| Mutation::Replace { address, opcode }),
+                    ),
+                    (Operator::Adjust(k), Some(i)) => mutations.extend(
+                        (-k..=*k)
+                            .filter(|&d| d != 0 && i.argument.checked_add(d).is_some())
+                            .map(|delta| Mutation::Adjust { address, delta }),
+                    ),
+                    (Operator::Delete, Some(_)) => mutations.push(Mutation::Delete { address }),
+                }
+            }
+        }
+        mutations
+    }
+
+    /// All candidates, fewest edits first, produced as they are needed.
+    pub fn candidates<'a>(
+        &self,
+        program: &'a [Instruction],
+    ) -> impl Iterator<Item = Candidate<'a>> + 'a {
+        let mutations = self.mutations(program);
+        (1..=self.max_mutations).flat_map(move |n| {
+            mutations
+                .clone()
+                .into_iter()
+                .combinations(n)
+                .filter(|ms| ms.windows(2).all(|w| w[0].address() < w[1].address()))
+                .map(move |mutations| Candidate {
+                    base: program,
+                    mutations,
+                })
+        })
+    }
+
+    /// The first candidate whose execution satisfies `predicate`, with the result.
+    pub fn search<'a, F>(
+        &self,
+        program: &'a [Instruction],
+        predicate: F,
+    ) -> Option<(Candidate<'a>, ExecutionResult)>
+    where
+        F: Fn(&ExecutionResult) -> bool,
+    {
+        let executor = CodeExecutor::new();
+        self.candidates(program)
+            .map(|candidate| {
+                let result = executor.execute(&candidate);
+                (candidate, result)
+            })
+            .find(|(_, result)| predicate(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::super::vm::fixtures::{example, program};
+    use super::super::ExecutionState;
+    use super::*;
+
+    #[test]
+    fn candidate_views() {
+        let base = program(&["acc +1", "acc +2", "acc +3"]);
+        let view = |mutations: Vec<Mutation>| {
+            Candidate {
+                base: &base,
+                mutations,
+            }
+            .to_program()
+        };
+        assert_eq!(
+            program(&["acc +1", "nop +2", "acc +3"]),
+            view(vec![Mutation::Replace {
+                address: 1,
+                opcode: Opcode::Nop
+            }])
+        );
+        assert_eq!(
+            program(&["acc +1", "acc +3"]),
+            view(vec![Mutation::Delete { address: 1 }])
+        );
+        let nop = Instruction::new(Opcode::Nop, 0);
+        assert_eq!(
+            program(&["nop +0", "acc +1", "acc +5", "nop +0"]),
+            view(vec![
+                Mutation::Insert {
+                    address: 0,
+                    instruction: nop
+                },
+                Mutation::Delete { address: 1 },
+                Mutation::Adjust {
+                    address: 2,
+                    delta: 2
+                },
+                Mutation::Insert {
+                    address: 3,
+                    instruction: nop
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn candidates_are_lazy_and_ordered() {
+        let base = program(&["jmp +0", "acc +1", "nop +0"]);
+        let mutator = Mutator::new(vec![Operator::Flip, Operator::Delete], 2);
+        let counts = mutator
+            .candidates(&base)
+            .map(|c| c.mutations().len())
+            .counts();
+        // flips at 0 and 2, deletes at 0, 1 and 2; pairs need distinct addresses
+        assert_eq!(Some(&5), counts.get(&1));
+        assert_eq!(Some(&8), counts.get(&2));
+        let first = mutator.candidates(&base).next().unwrap();
+        assert_eq!(
+            &[Mutation::Replace {
+                address: 0,
+                opcode: Opcode::Nop
+            }],
+            first.mutations()
+        );
+    }
+
+    #[test]
+    fn search_repairs_the_example() {
+        let base = example();
+        let (candidate, result) = Mutator::new(vec![Operator::Flip], 1)
+            .search(&base, |r| matches!(r, ExecutionResult::Success(_)))
+            .unwrap();
+        assert_eq!(
+            &[Mutation::Replace {
+                address: 7,
+                opcode: Opcode::Nop
+            }],
+            candidate.mutations()
+        );
+        assert_eq!(
+            ExecutionResult::Success(ExecutionState {
+                accumulator: 8,
+                address: 9
+            }),
+            result
+        );
+    }
+
+    #[test]
+    fn search_with_other_operators() {
+        let base = example();
+        let success = |r: &ExecutionResult| matches!(r, ExecutionResult::Success(_));
+        // deleting the final backwards jump also breaks the loop
+        let (candidate, result) = Mutator::new(vec![Operator::Delete], 1)
+            .search(&base, success)
+            .unwrap();
+        assert_eq!(&[Mutation::Delete { address: 7 }], candidate.mutations());
+        assert_eq!(
+            ExecutionResult::Success(ExecutionState {
+                accumulator: 8,
+                address: 8
+            }),
+            result
+        );
+        // a predicate on the accumulator
+        let (candidate, _) = Mutator::new(vec![Operator::Adjust(3)], 2)
+            .search(
+                &base,
+                |r| matches!(r, ExecutionResult::InfiniteLoop(s) if s.accumulator == 10),
+            )
+            .unwrap();
+        assert_eq!(2, candidate.mutations().len());
+        assert!(
+            Mutator::new(vec![Operator::Insert(Instruction::new(Opcode::Jmp, 0))], 1)
+                .search(&base, success)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn parsing_operators() {
+        assert_eq!(
+            Operator::Adjust(MAX_ADJUSTMENT),
+            format!("adjust={}", MAX_ADJUSTMENT).parse().unwrap()
+        );
+        assert_eq!(
+            "expected an adjustment from 1 to 1000, got \"100000000\"",
+            "adjust=100000000"
+                .parse::<Operator>()
+                .unwrap_err()
+                .to_string()
+        );
+        assert!("adjust=0".parse::<Operator>().is_err());
+        assert_eq!(
+            Operator::Insert(Instruction::new(Opcode::Nop, 0)),
+            "insert=nop +0".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn adjustments_that_overflow_are_skipped() {
+        let base = vec![
+            Instruction::new(Opcode::Acc, i32::MAX - 1),
+            Instruction::new(Opcode::Jmp, i32::MIN),
+        ];
+        let deltas: Vec<i32> = Mutator::new(vec![Operator::Adjust(2)], 1)
+            .mutations(&base)
+            .into_iter()
+            .map(|m| match m {
+                Mutation::Adjust { delta, .. } => delta,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(vec![-2, -1, 1, 1, 2], deltas);
+    }
+}
@@ -1,30 +1,42 @@
-use super::super::utils::{parse_as, read_strings_from_file};
+use super::super::error::Result;
+use super::super::utils::parse_as;
+use super::{Answer, Solution};
+use log::{debug, trace};
 use nalgebra::DMatrix;
 
-pub fn solve() {
-    let strings = read_strings_from_file("./inputs/day09_1").expect("Failed to read inputs");
-    println!("Problem 1: {:?}", problem_1(&strings, 25));
-    println!("Problem 2: {:?}", problem_2(&strings, 25));
+pub struct Day09 {
+    pub preamble_len: usize,
 }
 
-pub fn problem_1(strings: &Vec<String>, preamble_len: usize) -> u64 {
-    let numbers: Vec<u64> = parse_as::<u64>(strings);
-    println!("{:?}", numbers);
-    let n = first_number_that_is_not_sum_of_preamble(&numbers, preamble_len).unwrap();
-    n
-}
+impl Solution for Day09 {
+    type Input = Vec<u64>;
+
+    fn parse(&self, strings: &[String]) -> Result<Self::Input> {
+        let numbers: Vec<u64> = parse_as::<u64>(strings)?;
+        debug!("{:?}", numbers);
+        Ok(numbers)
+    }
+
+    fn part1(&self, numbers: &Self::Input) -> Answer {
+        first_number_that_is_not_sum_of_preamble(numbers, self.preamble_len).into()
+    }
 
-pub fn problem_2(strings: &Vec<String>, preamble_len: usize) -> u64 {
-    let numbers: Vec<u64> = parse_as::<u64>(strings);
-    println!("{:?}", numbers);
-    let n = first_number_that_is_not_sum_of_preamble(&numbers, preamble_len).unwrap();
-    println!("Not sum of preamble number: {:?}", n);
-    let seq: &[u64] = find_sequence_summing_up_to(&numbers, n);
-    println!("Seq summing up to n: {:?}", seq);
-    let min = seq.iter().min().unwrap();
-    let max = seq.iter().max().unwrap();
-    println!("Min: {:?}, Max: {:?}", min, max);
-    min + max
+    fn part2(&self, numbers: &Self::Input) -> Answer {
+        let n = match first_number_that_is_not_sum_of_preamble(numbers, self.preamble_len) {
+            Some(n) => n,
+            None => return Answer::Unsolved,
+        };
+        debug!("Not sum of preamble number: {:?}", n);
+        let seq: &[u64] = match find_sequence_summing_up_to(numbers, n) {
+            Some(seq) => seq,
+            None => return Answer::Unsolved,
+        };
+        debug!("Seq summing up to n: {:?}", seq);
+        let min = seq.iter().min().unwrap();
+        let max = seq.iter().max().unwrap();
+        debug!("Min: {:?}, Max: {:?}", min, max);
+        (min + max).into()
+    }
 }
 
 #[derive(Debug)]
@@ -34,7 +46,7 @@ struct CrossSums {
 
 impl CrossSums {
     fn new(preamble: &[u64]) -> Self {
-        let numbers: Vec<u64> = preamble.iter().cloned().collect();
+        let numbers: Vec<u64> = preamble.to_vec();
         Self { numbers }
     }
 
@@ -59,19 +71,16 @@ impl CrossSums {
     }
 }
 
-fn first_number_that_is_not_sum_of_preamble(
-    numbers: &Vec<u64>,
-    preamble_len: usize,
-) -> Option<u64> {
-    let mut sums = CrossSums::new(&numbers[..preamble_len]);
-    for i in preamble_len..numbers.len() {
-        // println!("Testing numbers[{:?}] = {:?}", i, numbers[i]);
-        if sums.contains(numbers[i]) {
-            // println!("Sums {:?} contains {:?}", sums, numbers[i]);
-            sums.push(numbers[i]);
+fn first_number_that_is_not_sum_of_preamble(numbers: &[u64], preamble_len: usize) -> Option<u64> {
+    let mut sums = CrossSums::new(numbers.get(..preamble_len)?);
+    for &n in numbers.iter().skip(preamble_len) {
+        trace!("Testing {:?}", n);
+        if sums.contains(n) {
+            trace!("Sums {:?} contains {:?}", sums, n);
+            sums.push(n);
         } else {
-            // println!("Sums {:?} does not contain {:?}", sums, numbers[i]);
-            return Some(numbers[i]);
+            trace!("Sums {:?} does not contain {:?}", sums, n);
+            return Some(n);
         }
     }
     None
@@ -80,6 +89,9 @@ fn first_number_that_is_not_sum_of_preamble(
 type IntMatrix = DMatrix<u64>;
 
 fn dp_sequence_find(numbers: &[u64], desired_sum: u64) -> Option<&[u64]> {
+    if numbers.is_empty() {
+        return None;
+    }
     let mut sums = IntMatrix::from_element(numbers.len(), numbers.len(), 0);
     //println!("{:?}", sums);
     sums[(0, 0)] = numbers[0];
@@ -104,8 +116,8 @@ fn dp_sequence_find(numbers: &[u64], desired_sum: u64) -> Option<&[u64]> {
     None
 }
 
-fn find_sequence_summing_up_to(numbers: &Vec<u64>, desired_sum: u64) -> &[u64] {
-    dp_sequence_find(numbers, desired_sum).unwrap()
+fn find_sequence_summing_up_to(numbers: &[u64], desired_sum: u64) -> Option<&[u64]> {
+    dp_sequence_find(numbers, desired_sum)
 }
 
 #[cfg(test)]
@@ -124,7 +136,7 @@ mod tests {
     fn find_sequence_summing_up_to_1() {
         let numbers = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
         assert_eq!(
-            vec![1, 2, 3, 4, 5].as_slice(),
+            Some(vec![1, 2, 3, 4, 5].as_slice()),
             find_sequence_summing_up_to(&numbers, 15)
         );
     }
@@ -138,7 +150,8 @@ mod tests {
         .iter()
         .map(|s| s.to_string())
         .collect();
-        assert_eq!(127, problem_1(&strings, 5));
+        let day = Day09 { preamble_len: 5 };
+        assert_eq!(Answer::from(127), day.part1(&day.parse(&strings).unwrap()));
     }
 
     #[test]
@@ -150,6 +163,17 @@ mod tests {
         .iter()
         .map(|s| s.to_string())
         .collect();
-        assert_eq!(62, problem_2(&strings, 5));
+        let day = Day09 { preamble_len: 5 };
+        assert_eq!(Answer::from(62), day.part2(&day.parse(&strings).unwrap()));
+    }
+
+    #[test]
+    fn shorter_than_the_preamble() {
+        let strings: Vec<String> = ["35", "20", "15"].iter().map(|s| s.to_string()).collect();
+        let day = Day09 { preamble_len: 25 };
+        let numbers = day.parse(&strings).unwrap();
+        assert_eq!(Answer::Unsolved, day.part1(&numbers));
+        assert_eq!(Answer::Unsolved, day.part2(&numbers));
+        assert_eq!(Answer::Unsolved, day.part2(&vec![]));
     }
 }
@@ -1,37 +1,132 @@
-use super::super::utils::{parse_as, read_strings_from_file};
+use super::super::error::Result;
+use super::super::utils::parse_as;
+use super::{Answer, Solution};
+use log::debug;
 
-pub fn solve() {
-    let strings = read_strings_from_file("./inputs/day10_1").expect("Failed to read inputs");
-    println!("Problem 1: {:?}", problem_1(&strings));
-    println!("Problem 2: {:?}", problem_2(&strings));
+pub struct Day10;
+
+impl Solution for Day10 {
+    type Input = Vec<u64>;
+
+    fn parse(&self, strings: &[String]) -> Result<Self::Input> {
+        parse_as::<u64>(strings)
+    }
+
+    fn part1(&self, adapters: &Self::Input) -> Answer {
+        joltage_differences(adapters)
+            .map(|diffs| {
+                debug!("differences {:?}", diffs);
+                diffs[1] * diffs[3]
+            })
+            .into()
+    }
+
+    fn part2(&self, adapters: &Self::Input) -> Answer {
+        number_of_arrangements(adapters).into()
+    }
 }
 
-pub fn problem_1(strings: &Vec<String>) -> u64 {
-    println!("{:?}", strings);
-    1
+/// The outlet (0 jolts), every adapter in ascending order, and the device (max + 3).
+fn joltage_chain(adapters: &[u64]) -> Vec<u64> {
+    let mut chain = Vec::with_capacity(adapters.len() + 2);
+    chain.push(0);
+    chain.extend_from_slice(adapters);
+    chain.sort_unstable();
+    chain.push(chain.last().unwrap() + 3);
+    chain
 }
 
-pub fn problem_2(strings: &Vec<String>) -> u64 {
-    println!("{:?}", strings);
-    2
+/// How many steps of 1, 2 and 3 jolts the full chain takes, indexed by step size,
+/// or `None` when some gap cannot be bridged.
+fn joltage_differences(adapters: &[u64]) -> Option<[usize; 4]> {
+    let mut diffs = [0; 4];
+    for pair in joltage_chain(adapters).windows(2) {
+        match pair[1] - pair[0] {
+            d @ 1..=3 => diffs[d as usize] += 1,
+            _ => return None,
+        }
+    }
+    Some(diffs)
+}
+
+/// Counts the ways to get from the outlet to the device, where `ways[i]` is
+/// the number of arrangements ending at the i-th adapter of the chain. The
+/// count grows exponentially with the chain, `None` once it overflows `u128`.
+fn number_of_arrangements(adapters: &[u64]) -> Option<u128> {
+    let chain = joltage_chain(adapters);
+    let mut ways = vec![0u128; chain.len()];
+    ways[0] = 1;
+    for i in 1..chain.len() {
+        ways[i] = (i.saturating_sub(3)..i)
+            .filter(|&j| chain[i] - chain[j] <= 3)
+            .try_fold(0u128, |sum, j| sum.checked_add(ways[j]))?;
+    }
+    Some(ways[chain.len() - 1])
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn cross_sum_1() {}
+    fn small_example() -> Vec<String> {
+        ["16", "10", "15", "5", "1", "11", "7", "19", "6", "12", "4"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
 
-    #[test]
-    fn test2() {
-        let strings: Vec<String> = vec![
-            "35", "20", "15", "25", "47", "40", "62", "55", "65", "95", "102", "117", "150", "182",
-            "127", "219", "299", "277", "309", "576",
+    fn large_example() -> Vec<String> {
+        [
+            "28", "33", "18", "42", "31", "14", "46", "20", "48", "47", "24", "23", "49", "45",
+            "19", "38", "39", "11", "1", "32", "25", "35", "8", "17", "7", "9", "4", "2", "34",
+            "10", "3",
         ]
         .iter()
         .map(|s| s.to_string())
-        .collect();
-        assert_eq!(62, problem_2(&strings));
+        .collect()
+    }
+
+    #[test]
+    fn differences() {
+        let adapters = Day10.parse(&small_example()).unwrap();
+        assert_eq!(Some([0, 7, 0, 5]), joltage_differences(&adapters));
+        let adapters = Day10.parse(&large_example()).unwrap();
+        assert_eq!(Some([0, 22, 0, 10]), joltage_differences(&adapters));
+        assert_eq!(None, joltage_differences(&[1, 5]));
+    }
+
+    #[test]
+    fn example_1() {
+        assert_eq!(
+            Answer::from(35),
+            Day10.part1(&Day10.parse(&small_example()).unwrap())
+        );
+        assert_eq!(
+            Answer::from(220),
+            Day10.part1(&Day10.parse(&large_example()).unwrap())
+        );
+    }
+
+    #[test]
+    fn example_2() {
+        assert_eq!(
+            Answer::from(8),
+            Day10.part2(&Day10.parse(&small_example()).unwrap())
+        );
+        assert_eq!(
+            Answer::from(19208),
+            Day10.part2(&Day10.parse(&large_example()).unwrap())
+        );
+    }
+
+    #[test]
+    fn large_counts() {
+        let adapters: Vec<u64> = (1..=100).collect();
+        assert_eq!(
+            Answer::Text("180396380815100901214157639".to_string()),
+            Day10.part2(&adapters)
+        );
+        let adapters: Vec<u64> = (1..=200).collect();
+        assert_eq!(Answer::Unsolved, Day10.part2(&adapters));
     }
 }
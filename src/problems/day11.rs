@@ -1,180 +1,280 @@
-use super::super::utils::{read_strings_from_file};
-use nalgebra::{DMatrix};
+use super::super::automaton::{Automaton, Neighbours, Outcome, Simulation};
+use super::super::error::Result;
+use super::super::grid::{Grid, Point, NEIGHBOURS_8};
+use super::{Answer, Solution};
+use log::{debug, error, warn};
 use std::fmt;
-use itertools::Itertools;
-use std::collections::HashSet;
+use std::thread;
 
-pub fn solve() {
-    let strings = read_strings_from_file("./inputs/day11_1").expect("Failed to read inputs");
-    println!("Problem 1: {:?}", problem_1(&strings));
-    println!("Problem 2: {:?}", problem_2(&strings));
-}
+mod packed;
+
+use packed::{PackedSeats, PackedSimulation};
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[repr(u8)]
-enum Seat {
+pub enum Seat {
     Floor = 0,
     Empty = 1,
     Occupied = 2,
 }
 
-type SeatGrid = DMatrix<Seat>;
+impl From<Seat> for char {
+    fn from(seat: Seat) -> char {
+        match seat {
+            Seat::Floor => '.',
+            Seat::Empty => 'L',
+            Seat::Occupied => '#',
+        }
+    }
+}
+
+type SeatGrid = Grid<Seat>;
 
-#[derive(Debug)]
-struct SeatLayout {
-    grid: SeatGrid,
+/// Real layouts settle within a hundred or so generations.
+const MAX_GENERATIONS: usize = 10_000;
+
+/// Stored as bitboards; rules that need to look at cells one by one work on
+/// the unpacked `grid()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeatLayout {
+    seats: PackedSeats,
 }
 
 impl SeatLayout {
-    fn from_input(strings: &Vec<String>) -> Self {
-        let first_string = strings.first().unwrap();
-        let mut grid = SeatGrid::from_element(strings.len(), first_string.len(), Seat::Floor);
-        for (r, row) in strings.iter().enumerate() {
-            for (c, ch) in row.chars().enumerate() {
-                grid[(r, c)] = match ch {
-                    'L' => Seat::Empty,
-                    '#' => Seat::Occupied,
-                    _ => Seat::Floor,
-                }
-            }
-        }
-        Self { grid }
+    fn from_input(strings: &[String]) -> Result<Self> {
+        let grid = SeatGrid::parse(strings, |ch| match ch {
+            'L' => Some(Seat::Empty),
+            '#' => Some(Seat::Occupied),
+            '.' => Some(Seat::Floor),
+            _ => None,
+        })?;
+        Ok(Self::from_grid(&grid))
     }
 
-    // fn cells(&self) -> impl Iterator<Item=(usize, usize, Seat)>  {
-    //     self.grid.row_iter()
-    //         .enumerate()
-    //         .flat_map(|(r, row)|
-    //             row.iter().enumerate().map(move |(c, seat)| (r, c, seat)).cloned()
-    //         )
-    // }
-
-    fn number_of_occupied_seats_around(&self, r: usize, c: usize) -> usize {
-        let (nrows, ncols) = self.grid.shape();
-        let mut directions: HashSet<(i8, i8)> = [
-            (-1, -1), (-1, 0), (-1, 1),
-            (0, -1),           (0, 1),
-            (1, -1),  (1, 0),  (1, 1),
-        ].iter().cloned().collect();
-        if r == 0 {
-            directions.remove(&(-1, -1));
-            directions.remove(&(-1, 0));
-            directions.remove(&(-1, 1));
-        }
-        if r == nrows - 1 {
-            directions.remove(&(1, -1));
-            directions.remove(&(1, 0));
-            directions.remove(&(1, 1));
-        }
-        if c == 0 {
-            directions.remove(&(-1, -1));
-            directions.remove(&(0, -1));
-            directions.remove(&(1, -1));
+    fn from_grid(grid: &SeatGrid) -> Self {
+        Self {
+            seats: PackedSeats::from_grid(grid),
         }
-        if c == ncols - 1 {
-            directions.remove(&(-1, 1));
-            directions.remove(&(0, 1));
-            directions.remove(&(1, 1));
+    }
+
+    /// A random `width` x `height` layout, a quarter of it floor, for benchmarks.
+    pub fn synthetic(width: usize, height: usize, mut seed: u64) -> Self {
+        let seats = (0..width * height)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                if seed.is_multiple_of(4) {
+                    Seat::Floor
+                } else {
+                    Seat::Empty
+                }
+            })
+            .collect();
+        Self::from_grid(&SeatGrid::from_cells(width, height, seats))
+    }
+
+    pub fn width(&self) -> usize {
+        self.seats.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.seats.height()
+    }
+
+    /// The layout one cell at a time.
+    pub fn grid(&self) -> Grid<Seat> {
+        self.seats.to_grid()
+    }
+
+    /// Every generation of the part's seating rule, from this layout up to
+    /// the first one nobody leaves (or `MAX_GENERATIONS`).
+    pub fn evolution(&self, part: u8) -> Vec<SeatLayout> {
+        let rule = if part == 1 { &ADJACENT } else { &VISIBLE };
+        let mut simulation = self.simulation(rule, 1);
+        let mut frames = vec![self.clone()];
+        while frames.len() <= MAX_GENERATIONS && simulation.step() > 0 {
+            frames.push(self.with_seats(simulation.cells().to_vec()));
         }
-        let n: usize  = directions.into_iter()
-            .map(|d| ((r as i8 + d.0) as usize, (c as i8 + d.1) as usize))
-            .map(|d| self.grid[d] )
-            .map(|s| match s { Seat::Occupied => 1usize, _ => 0usize })
-            .sum();
-        n
+        frames
     }
 
-    fn next_generation_cell(&self, r: usize, c: usize) -> Seat {
-        match self.grid[(r, c)] {
-            Seat::Empty => {
-                if self.number_of_occupied_seats_around(r, c) == 0 { Seat::Occupied } else { Seat::Empty }
-            },
-            Seat::Occupied => {
-                if self.number_of_occupied_seats_around(r, c) >= 4 { Seat::Empty } else { Seat::Occupied }
+    fn adjacent_seats(grid: &SeatGrid, p: Point) -> Vec<Point> {
+        grid.neighbours8(p)
+            .filter(|(_, &s)| s != Seat::Floor)
+            .map(|(q, _)| q)
+            .collect()
+    }
+
+    /// The first seat along each of the eight directions, looking past the floor.
+    fn visible_seats(grid: &SeatGrid, p: Point) -> Vec<Point> {
+        NEIGHBOURS_8
+            .iter()
+            .filter_map(|&d| grid.ray(p, d).find(|(_, &s)| s != Seat::Floor))
+            .map(|(q, _)| q)
+            .collect()
+    }
+
+    #[cfg(test)]
+    fn number_of_occupied_seats_in_view(&self, r: usize, c: usize, rule: &SeatingRule) -> usize {
+        let grid = self.grid();
+        (rule.seats_in_view)(&grid, Point::new(c as isize, r as isize))
+            .into_iter()
+            .filter(|&q| grid[q] == Seat::Occupied)
+            .count()
+    }
+
+    /// An automaton over the seats of this layout. Floor never changes, so who
+    /// sees whom is worked out once for the whole simulation.
+    fn simulation(
+        &self,
+        rule: &SeatingRule,
+        threads: usize,
+    ) -> Automaton<Seat, impl Fn(&Seat, Neighbours<Seat>) -> Seat> {
+        let tolerance = rule.tolerance;
+        let seats_in_view = rule.seats_in_view;
+        let grid = self.grid();
+        Automaton::new(
+            grid.cells().to_vec(),
+            |i| {
+                (seats_in_view)(&grid, grid.point_of(i))
+                    .into_iter()
+                    .map(|q| grid.index_of(q).unwrap())
             },
-            _ => {
-                self.grid[(r, c)]
+            move |&seat, in_view| {
+                let mut occupied = in_view.filter(|&&s| s == Seat::Occupied);
+                match seat {
+                    Seat::Empty if occupied.next().is_none() => Seat::Occupied,
+                    Seat::Occupied if occupied.count() >= tolerance => Seat::Empty,
+                    seat => seat,
+                }
             },
-        }
+        )
+        .with_threads(threads)
+    }
+
+    /// The part 2 rule as an automaton stepping on `threads` threads, the way
+    /// `settle` runs it.
+    pub fn visibility_simulation(
+        &self,
+        threads: usize,
+    ) -> Automaton<Seat, impl Fn(&Seat, Neighbours<Seat>) -> Seat> {
+        self.simulation(&VISIBLE, threads)
     }
 
-    fn next_generation(&self) -> Self {
-        let mut other = Self { grid: self.grid.clone() };
-        let (nrows, ncols) = self.grid.shape();
-        for r in 0..nrows {
-            for c in 0..ncols {
-                other.grid[(r, c)] = self.next_generation_cell(r, c);
+    fn with_seats(&self, seats: Vec<Seat>) -> Self {
+        Self::from_grid(&SeatGrid::from_cells(self.width(), self.height(), seats))
+    }
+
+    /// One step of `rule`, cell by cell.
+    #[cfg(test)]
+    fn next_generation(&self, rule: &SeatingRule) -> Self {
+        let mut simulation = self.simulation(rule, 1);
+        simulation.step();
+        self.with_seats(simulation.into_cells())
+    }
+
+    /// Applies `rule` until nobody moves any more, or `None` if people keep
+    /// shuffling around in circles or take too long to make up their minds.
+    fn settle(&self, rule: &SeatingRule) -> Option<Self> {
+        let (outcome, settled) = if rule.packs {
+            let mut simulation = PackedSimulation::new(self.seats.clone(), rule.tolerance);
+            (
+                simulation.run(MAX_GENERATIONS),
+                Self {
+                    seats: simulation.into_seats(),
+                },
+            )
+        } else {
+            let threads = thread::available_parallelism().map_or(1, |n| n.get());
+            let mut simulation = self.simulation(rule, threads);
+            (
+                simulation.run(MAX_GENERATIONS),
+                self.with_seats(simulation.into_cells()),
+            )
+        };
+        match outcome {
+            Ok(Outcome::FixedPoint { generation }) => {
+                debug!("stable after {} generations", generation);
+                Some(settled)
+            }
+            Ok(Outcome::Cycle { start, length }) => {
+                warn!(
+                    "seating repeats every {} generations from generation {}",
+                    length, start
+                );
+                None
+            }
+            Err(e) => {
+                error!("{}", e);
+                None
             }
         }
-        other
     }
 
+    #[cfg(test)]
     fn invert(&self) -> Self {
-        let mut other = Self { grid: self.grid.clone() };
-        let (nrows, ncols) = self.grid.shape();
-        for r in 0..nrows {
-            for c in 0..ncols {
-                other.grid[(r, c)] = match self.grid[(r, c)] {
-                    Seat::Empty => Seat::Occupied ,
-                    Seat::Occupied => Seat::Empty,
-                    _ => self.grid[(r, c)],
-                }
-            }
+        Self {
+            seats: self.seats.invert(),
         }
-        other
     }
 
-    fn number_of_occupied_seats(&self) -> usize {
-        let mut n = 0;
-        let (nrows, ncols) = self.grid.shape();
-        for r in 0..nrows {
-            for c in 0..ncols {
-                match self.grid[(r, c)] {
-                    Seat::Occupied => { n += 1; },
-                    _ => {},
-                }
-            }
-        }
-        n
+    pub fn number_of_occupied_seats(&self) -> usize {
+        self.seats.number_of_occupied_seats()
     }
 }
 
 impl fmt::Display for SeatLayout {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for row in self.grid.row_iter() {
-            let line = row.iter()
-                .map(|s| match s {
-                    Seat::Floor => '.',
-                    Seat::Empty => 'L',
-                    Seat::Occupied => '#',
-                })
-                .join("");
-            write!(f, "{:}\n", line)?;
-        }
-        Ok(())
+        write!(f, "{}", self.grid())
     }
 }
 
-pub fn problem_1(strings: &Vec<String>) -> usize {
-    println!("{:?}", strings);
-    let mut layout = SeatLayout::from_input(strings);
-    // println!("{:}", layout);
-    loop {
-        let other = layout.next_generation();
-        // println!("{:}", other);
-        if layout.to_string() == other.to_string() {
-            break;
-        }
-        layout = other;
-    }
-    layout.number_of_occupied_seats()
+/// How people pick seats: whom they take into account and how many occupied
+/// seats it takes for them to leave.
+struct SeatingRule {
+    seats_in_view: fn(&SeatGrid, Point) -> Vec<Point>,
+    tolerance: usize,
+    /// Whether the rule only looks at adjacent seats, so it can step on bitboards.
+    packs: bool,
 }
 
-pub fn problem_2(strings: &Vec<String>) -> u64 {
-    println!("{:?}", strings);
-    2
-}
+const ADJACENT: SeatingRule = SeatingRule {
+    seats_in_view: SeatLayout::adjacent_seats,
+    tolerance: 4,
+    packs: true,
+};
+
+const VISIBLE: SeatingRule = SeatingRule {
+    seats_in_view: SeatLayout::visible_seats,
+    tolerance: 5,
+    packs: false,
+};
+
+pub struct Day11;
+
+impl Solution for Day11 {
+    type Input = SeatLayout;
 
+    fn parse(&self, strings: &[String]) -> Result<Self::Input> {
+        debug!("{:?}", strings);
+        SeatLayout::from_input(strings)
+    }
+
+    fn part1(&self, input: &Self::Input) -> Answer {
+        input
+            .settle(&ADJACENT)
+            .map(|layout| layout.number_of_occupied_seats())
+            .into()
+    }
+
+    fn part2(&self, input: &Self::Input) -> Answer {
+        input
+            .settle(&VISIBLE)
+            .map(|layout| layout.number_of_occupied_seats())
+            .into()
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -182,7 +282,7 @@ mod tests {
 
     #[test]
     fn number_of_occupied_seats_around() {
-        let strings: Vec<String> = vec![
+        let strings: Vec<String> = [
             "L.LL.LL.LL",
             "LLLLLLL.LL",
             "L.L.L..L..",
@@ -194,23 +294,23 @@ mod tests {
             "L.LLLLLL.L",
             "L.LLLLL.LL",
         ]
-            .iter()
-            .map(|s| s.to_string())
-            .collect();
-        let layout = SeatLayout::from_input(&strings);
-        assert_eq!(0, layout.number_of_occupied_seats_around(0, 0));
-        assert_eq!(0, layout.number_of_occupied_seats_around(9, 9));
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let layout = SeatLayout::from_input(&strings).unwrap();
+        assert_eq!(0, layout.number_of_occupied_seats_in_view(0, 0, &ADJACENT));
+        assert_eq!(0, layout.number_of_occupied_seats_in_view(9, 9, &ADJACENT));
         let other = layout.invert();
-        assert_eq!(2, other.number_of_occupied_seats_around(0, 0));
-        assert_eq!(2, other.number_of_occupied_seats_around(9, 9));
-        assert_eq!(3, other.number_of_occupied_seats_around(0, 9));
-        assert_eq!(1, other.number_of_occupied_seats_around(9, 0));
-        assert_eq!(6, other.number_of_occupied_seats_around(4, 5));
+        assert_eq!(2, other.number_of_occupied_seats_in_view(0, 0, &ADJACENT));
+        assert_eq!(2, other.number_of_occupied_seats_in_view(9, 9, &ADJACENT));
+        assert_eq!(3, other.number_of_occupied_seats_in_view(0, 9, &ADJACENT));
+        assert_eq!(1, other.number_of_occupied_seats_in_view(9, 0, &ADJACENT));
+        assert_eq!(6, other.number_of_occupied_seats_in_view(4, 5, &ADJACENT));
     }
 
     #[test]
     fn step2() {
-        let strings: Vec<String> = vec![
+        let strings: Vec<String> = [
             "L.LL.LL.LL",
             "LLLLLLL.LL",
             "L.L.L..L..",
@@ -222,24 +322,24 @@ mod tests {
             "L.LLLLLL.L",
             "L.LLLLL.LL",
         ]
-            .iter()
-            .map(|s| s.to_string())
-            .collect();
-        let layout = SeatLayout::from_input(&strings);
-        let step1 = layout.next_generation();
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let layout = SeatLayout::from_input(&strings).unwrap();
+        let step1 = layout.next_generation(&ADJACENT);
         println!("step1\n{:}", step1);
-        let step2 = step1.next_generation();
+        let step2 = step1.next_generation(&ADJACENT);
         println!("step2\n{:}", step2);
-        assert_eq!(1, step2.number_of_occupied_seats_around(0, 0));
-        assert_eq!(1, step2.number_of_occupied_seats_around(9, 9));
-        assert_eq!(2, step2.number_of_occupied_seats_around(0, 9));
-        assert_eq!(1, step2.number_of_occupied_seats_around(9, 0));
-        assert_eq!(1, step2.number_of_occupied_seats_around(4, 5));
+        assert_eq!(1, step2.number_of_occupied_seats_in_view(0, 0, &ADJACENT));
+        assert_eq!(1, step2.number_of_occupied_seats_in_view(9, 9, &ADJACENT));
+        assert_eq!(2, step2.number_of_occupied_seats_in_view(0, 9, &ADJACENT));
+        assert_eq!(1, step2.number_of_occupied_seats_in_view(9, 0, &ADJACENT));
+        assert_eq!(1, step2.number_of_occupied_seats_in_view(4, 5, &ADJACENT));
     }
 
     #[test]
     fn example_1() {
-        let strings: Vec<String> = vec![
+        let strings: Vec<String> = [
             "L.LL.LL.LL",
             "LLLLLLL.LL",
             "L.L.L..L..",
@@ -251,9 +351,126 @@ mod tests {
             "L.LLLLLL.L",
             "L.LLLLL.LL",
         ]
-            .iter()
-            .map(|s| s.to_string())
-            .collect();
-        assert_eq!(37, problem_1(&strings));
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(
+            Answer::from(37),
+            Day11.part1(&Day11.parse(&strings).unwrap())
+        );
+    }
+
+    fn layout_of(rows: &[&str]) -> SeatLayout {
+        let strings: Vec<String> = rows.iter().map(|s| s.to_string()).collect();
+        SeatLayout::from_input(&strings).unwrap()
+    }
+
+    #[test]
+    fn number_of_occupied_seats_visible() {
+        let layout = layout_of(&[
+            ".......#.",
+            "...#.....",
+            ".#.......",
+            ".........",
+            "..#L....#",
+            "....#....",
+            ".........",
+            "#........",
+            "...#.....",
+        ]);
+        assert_eq!(8, layout.number_of_occupied_seats_in_view(4, 3, &VISIBLE));
+
+        let layout = layout_of(&[".............", ".L.L.#.#.#.#.", "............."]);
+        assert_eq!(0, layout.number_of_occupied_seats_in_view(1, 1, &VISIBLE));
+
+        let layout = layout_of(&[
+            ".##.##.", "#.#.#.#", "##...##", "...L...", "##...##", "#.#.#.#", ".##.##.",
+        ]);
+        assert_eq!(0, layout.number_of_occupied_seats_in_view(3, 3, &VISIBLE));
+    }
+
+    #[test]
+    fn visible_step2() {
+        let layout = layout_of(&[
+            "L.LL.LL.LL",
+            "LLLLLLL.LL",
+            "L.L.L..L..",
+            "LLLL.LL.LL",
+            "L.LL.LL.LL",
+            "L.LLLLL.LL",
+            "..L.L.....",
+            "LLLLLLLLLL",
+            "L.LLLLLL.L",
+            "L.LLLLL.LL",
+        ]);
+        let step2 = layout.next_generation(&VISIBLE).next_generation(&VISIBLE);
+        assert_eq!(
+            [
+                "#.LL.LL.L#",
+                "#LLLLLL.LL",
+                "L.L.L..L..",
+                "LLLL.LL.LL",
+                "L.LL.LL.LL",
+                "L.LLLLL.LL",
+                "..L.L.....",
+                "LLLLLLLLL#",
+                "#.LLLLLL.L",
+                "#.LLLLL.L#"
+            ]
+            .join("\n")
+                + "\n",
+            step2.to_string()
+        );
+    }
+
+    #[test]
+    fn example_2() {
+        let strings: Vec<String> = [
+            "L.LL.LL.LL",
+            "LLLLLLL.LL",
+            "L.L.L..L..",
+            "LLLL.LL.LL",
+            "L.LL.LL.LL",
+            "L.LLLLL.LL",
+            "..L.L.....",
+            "LLLLLLLLLL",
+            "L.LLLLLL.L",
+            "L.LLLLL.LL",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(
+            Answer::from(26),
+            Day11.part2(&Day11.parse(&strings).unwrap())
+        );
+    }
+
+    #[test]
+    fn evolution_ends_at_the_fixed_point() {
+        let layout = layout_of(&[
+            "L.LL.LL.LL",
+            "LLLLLLL.LL",
+            "L.L.L..L..",
+            "LLLL.LL.LL",
+            "L.LL.LL.LL",
+            "L.LLLLL.LL",
+            "..L.L.....",
+            "LLLLLLLLLL",
+            "L.LLLLLL.L",
+            "L.LLLLL.LL",
+        ]);
+        let frames = layout.evolution(1);
+        assert_eq!(6, frames.len());
+        assert_eq!(layout, frames[0]);
+        assert_eq!(37, frames[5].number_of_occupied_seats());
+        assert_eq!(
+            26,
+            layout
+                .evolution(2)
+                .last()
+                .unwrap()
+                .number_of_occupied_seats()
+        );
     }
 }
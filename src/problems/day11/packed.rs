@@ -0,0 +1,280 @@
+//! Seat layouts as bitboards: one bit per cell in a seat mask and an occupied
+//! mask, each row padded to whole `u64` words. Neighbour counts are kept as
+//! four bit planes, so a generation costs a few dozen word operations per 64
+//! cells instead of eight lookups per cell. Only the adjacency rule steps
+//! this way; line of sight goes through the cell-by-cell automaton.
+
+use super::super::super::automaton::Simulation;
+use super::{Seat, SeatGrid};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PackedSeats {
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    /// Set for every seat, clear for floor and the padding past `width`.
+    seats: Vec<u64>,
+    /// Always a subset of `seats`.
+    occupied: Vec<u64>,
+}
+
+/// Adds a one-bit number in every lane to the four-bit counters in `planes`.
+fn add(planes: &mut [u64; 4], mut carry: u64) {
+    for plane in planes.iter_mut() {
+        let next = *plane & carry;
+        *plane ^= carry;
+        carry = next;
+    }
+}
+
+/// Lanes whose four-bit count is at least `threshold`, which has to fit in
+/// four bits as well.
+fn at_least(planes: &[u64; 4], threshold: usize) -> u64 {
+    debug_assert!(threshold < 16);
+    let mut greater = 0;
+    let mut equal = !0;
+    for bit in (0..4).rev() {
+        if threshold >> bit & 1 == 1 {
+            equal &= planes[bit];
+        } else {
+            greater |= equal & planes[bit];
+            equal &= !planes[bit];
+        }
+    }
+    greater | equal
+}
+
+impl PackedSeats {
+    pub fn from_grid(grid: &SeatGrid) -> Self {
+        let (width, height) = (grid.width(), grid.height());
+        let words_per_row = width.div_ceil(64);
+        let mut seats = vec![0; words_per_row * height];
+        let mut occupied = vec![0; words_per_row * height];
+        for (r, row) in grid.rows().enumerate() {
+            for (c, &seat) in row.iter().enumerate() {
+                let (word, bit) = (r * words_per_row + c / 64, 1 << (c % 64));
+                if seat != Seat::Floor {
+                    seats[word] |= bit;
+                }
+                if seat == Seat::Occupied {
+                    occupied[word] |= bit;
+                }
+            }
+        }
+        Self {
+            width,
+            height,
+            words_per_row,
+            seats,
+            occupied,
+        }
+    }
+
+    pub fn to_grid(&self) -> SeatGrid {
+        let mut cells = Vec::with_capacity(self.width * self.height);
+        for r in 0..self.height {
+            for c in 0..self.width {
+                let (word, bit) = (r * self.words_per_row + c / 64, 1 << (c % 64));
+                cells.push(
+                    match (self.seats[word] & bit != 0, self.occupied[word] & bit != 0) {
+                        (false, _) => Seat::Floor,
+                        (true, false) => Seat::Empty,
+                        (true, true) => Seat::Occupied,
+                    },
+                );
+            }
+        }
+        SeatGrid::from_cells(self.width, self.height, cells)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn number_of_occupied_seats(&self) -> usize {
+        self.occupied.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    #[cfg(test)]
+    pub fn invert(&self) -> Self {
+        let occupied = self
+            .seats
+            .iter()
+            .zip(&self.occupied)
+            .map(|(s, o)| s & !o)
+            .collect();
+        Self {
+            occupied,
+            ..self.clone()
+        }
+    }
+
+    /// Occupied neighbours of every cell in word `k` of row `r`, as bit planes.
+    fn neighbour_counts(&self, r: usize, k: usize) -> [u64; 4] {
+        let mut planes = [0; 4];
+        for rr in r.saturating_sub(1)..(r + 2).min(self.height) {
+            let row = &self.occupied[rr * self.words_per_row..(rr + 1) * self.words_per_row];
+            let word = row[k];
+            let previous = if k > 0 { row[k - 1] } else { 0 };
+            let next = row.get(k + 1).copied().unwrap_or(0);
+            add(&mut planes, word << 1 | previous >> 63);
+            add(&mut planes, word >> 1 | next << 63);
+            if rr != r {
+                add(&mut planes, word);
+            }
+        }
+        planes
+    }
+
+    /// The next generation under the adjacency rule, with the number of seats that changed.
+    pub fn next_generation(&self, tolerance: usize) -> (Self, usize) {
+        // nobody has more than 8 neighbours, so any higher tolerance means nobody leaves
+        let tolerance = tolerance.min(9);
+        let mut occupied = Vec::with_capacity(self.occupied.len());
+        let mut changed = 0;
+        for r in 0..self.height {
+            for k in 0..self.words_per_row {
+                let i = r * self.words_per_row + k;
+                let planes = self.neighbour_counts(r, k);
+                let nobody_around = !(planes[0] | planes[1] | planes[2] | planes[3]);
+                let (seats, taken) = (self.seats[i], self.occupied[i]);
+                let next =
+                    (seats & !taken & nobody_around) | (taken & !at_least(&planes, tolerance));
+                changed += (next ^ taken).count_ones() as usize;
+                occupied.push(next);
+            }
+        }
+        (
+            Self {
+                occupied,
+                ..self.clone()
+            },
+            changed,
+        )
+    }
+}
+
+/// The adjacency rule with the given tolerance, stepped on bitboards.
+pub struct PackedSimulation {
+    seats: PackedSeats,
+    tolerance: usize,
+    generation: usize,
+}
+
+impl PackedSimulation {
+    pub fn new(seats: PackedSeats, tolerance: usize) -> Self {
+        Self {
+            seats,
+            tolerance,
+            generation: 0,
+        }
+    }
+
+    pub fn into_seats(self) -> PackedSeats {
+        self.seats
+    }
+}
+
+impl Simulation for PackedSimulation {
+    /// Seats never move, so who sits where is the whole state.
+    type State = Vec<u64>;
+
+    fn step(&mut self) -> usize {
+        let (next, changed) = self.seats.next_generation(self.tolerance);
+        self.seats = next;
+        self.generation += 1;
+        changed
+    }
+
+    fn generation(&self) -> usize {
+        self.generation
+    }
+
+    fn state(&self) -> Vec<u64> {
+        self.seats.occupied.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::super::automaton::Outcome;
+    use super::super::{SeatLayout, ADJACENT};
+    use super::*;
+
+    fn example() -> SeatGrid {
+        let strings: Vec<String> = [
+            "L.LL.LL.LL",
+            "LLLLLLL.LL",
+            "L.L.L..L..",
+            "LLLL.LL.LL",
+            "L.LL.LL.LL",
+            "L.LLLLL.LL",
+            "..L.L.....",
+            "LLLLLLLLLL",
+            "L.LLLLLL.L",
+            "L.LLLLL.LL",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        SeatLayout::from_input(&strings).unwrap().grid()
+    }
+
+    #[test]
+    fn counting_planes() {
+        let mut planes = [0; 4];
+        for _ in 0..5 {
+            add(&mut planes, 0b110);
+        }
+        add(&mut planes, 0b100);
+        // lane 1 counts 5, lane 2 counts 6
+        assert_eq!([0b010, 0b100, 0b110, 0], planes);
+        assert_eq!(0b110, at_least(&planes, 4));
+        assert_eq!(0b110, at_least(&planes, 5));
+        assert_eq!(0b100, at_least(&planes, 6));
+        assert_eq!(0, at_least(&planes, 7));
+        assert_eq!(!0, at_least(&planes, 0));
+    }
+
+    #[test]
+    fn round_trip_and_invert() {
+        let grid = example();
+        let packed = PackedSeats::from_grid(&grid);
+        assert_eq!(grid, packed.to_grid());
+        assert_eq!(0, packed.number_of_occupied_seats());
+        assert_eq!(71, packed.invert().number_of_occupied_seats());
+    }
+
+    #[test]
+    fn example_settles_at_37() {
+        let mut simulation = PackedSimulation::new(PackedSeats::from_grid(&example()), 4);
+        assert_eq!(
+            Outcome::FixedPoint { generation: 5 },
+            simulation.run(100).unwrap()
+        );
+        assert_eq!(37, simulation.into_seats().number_of_occupied_seats());
+    }
+
+    #[test]
+    fn tolerances_beyond_eight_neighbours() {
+        let full = PackedSeats::from_grid(&example()).invert();
+        for &tolerance in &[9, 15, 16, 100] {
+            assert_eq!((full.clone(), 0), full.next_generation(tolerance));
+        }
+    }
+
+    #[test]
+    fn matches_cell_by_cell_across_word_boundaries() {
+        let mut layout = SeatLayout::synthetic(150, 40, 7);
+        let mut packed = PackedSeats::from_grid(&layout.grid());
+        for _ in 0..8 {
+            layout = layout.next_generation(&ADJACENT);
+            packed = packed.next_generation(4).0;
+            assert_eq!(layout.grid(), packed.to_grid());
+        }
+    }
+}
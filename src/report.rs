@@ -0,0 +1,210 @@
+use super::answers::Verdict;
+use super::problems::Answer;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Format {
+    /// One human readable line per answer
+    Text,
+    /// One JSON object per line
+    Json,
+    Csv,
+    /// Test Anything Protocol, wrong answers are `not ok`
+    Tap,
+}
+
+/// One solved part of a day.
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub day: u8,
+    pub part: u8,
+    pub answer: Answer,
+    pub elapsed: Duration,
+    pub checksum: String,
+    pub verdict: Verdict,
+}
+
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    day: u8,
+    part: u8,
+    answer: String,
+    elapsed_ns: u64,
+    checksum: &'a str,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected: Option<&'a str>,
+}
+
+impl Record {
+    fn expected(&self) -> Option<&str> {
+        match &self.verdict {
+            Verdict::Wrong { expected } => Some(expected),
+            _ => None,
+        }
+    }
+}
+
+pub struct Reporter<W: Write> {
+    format: Format,
+    out: W,
+    count: usize,
+}
+
+impl<W: Write> Reporter<W> {
+    pub fn new(format: Format, mut out: W) -> io::Result<Self> {
+        if format == Format::Csv {
+            writeln!(out, "day,part,answer,elapsed_ns,checksum,status,expected")?;
+        }
+        Ok(Self {
+            format,
+            out,
+            count: 0,
+        })
+    }
+
+    pub fn record(&mut self, r: &Record) -> io::Result<()> {
+        self.count += 1;
+        match self.format {
+            Format::Text => writeln!(
+                self.out,
+                "Day {:02} part {}: {} {}",
+                r.day, r.part, r.answer, r.verdict
+            ),
+            Format::Json => {
+                let json = JsonRecord {
+                    day: r.day,
+                    part: r.part,
+                    answer: r.answer.to_string(),
+                    elapsed_ns: r.elapsed.as_nanos() as u64,
+                    checksum: &r.checksum,
+                    status: r.verdict.status(),
+                    expected: r.expected(),
+                };
+                writeln!(
+                    self.out,
+                    "{}",
+                    serde_json::to_string(&json).expect("records always serialize")
+                )
+            }
+            Format::Csv => writeln!(
+                self.out,
+                "{},{},{},{},{},{},{}",
+                r.day,
+                r.part,
+                csv_field(&r.answer.to_string()),
+                r.elapsed.as_nanos(),
+                r.checksum,
+                r.verdict.status(),
+                r.expected().map_or(String::new(), csv_field)
+            ),
+            Format::Tap => {
+                let description = format!("day {:02} part {}: {}", r.day, r.part, r.answer);
+                match &r.verdict {
+                    Verdict::Correct => writeln!(self.out, "ok {} - {}", self.count, description),
+                    Verdict::Wrong { expected } => {
+                        writeln!(
+                            self.out,
+                            "not ok {} - {} # expected {}",
+                            self.count, description, expected
+                        )
+                    }
+                    Verdict::Unknown => {
+                        writeln!(
+                            self.out,
+                            "ok {} - {} # SKIP no recorded answer",
+                            self.count, description
+                        )
+                    }
+                }
+            }
+        }
+    }
+
+    /// TAP puts its plan last since the number of records is only known at the end.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.format == Format::Tap {
+            writeln!(self.out, "1..{}", self.count)?;
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> Vec<Record> {
+        vec![
+            Record {
+                day: 8,
+                part: 1,
+                answer: Answer::from(1594),
+                elapsed: Duration::from_nanos(1500),
+                checksum: "00000000000000ff".to_string(),
+                verdict: Verdict::Correct,
+            },
+            Record {
+                day: 8,
+                part: 2,
+                answer: Answer::Text("a,b".to_string()),
+                elapsed: Duration::from_nanos(20),
+                checksum: "00000000000000ff".to_string(),
+                verdict: Verdict::Wrong {
+                    expected: "758".to_string(),
+                },
+            },
+        ]
+    }
+
+    fn render(format: Format) -> String {
+        let mut reporter = Reporter::new(format, Vec::new()).unwrap();
+        for r in records().iter() {
+            reporter.record(r).unwrap();
+        }
+        String::from_utf8(reporter.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn json_lines() {
+        let out = render(Format::Json);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            r#"{"day":8,"part":1,"answer":"1594","elapsed_ns":1500,"checksum":"00000000000000ff","status":"correct"}"#,
+            lines[0]
+        );
+        assert!(lines[1].ends_with(r#""status":"wrong","expected":"758"}"#));
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(
+            "day,part,answer,elapsed_ns,checksum,status,expected\n\
+             8,1,1594,1500,00000000000000ff,correct,\n\
+             8,2,\"a,b\",20,00000000000000ff,wrong,758\n",
+            render(Format::Csv)
+        );
+    }
+
+    #[test]
+    fn tap() {
+        assert_eq!(
+            "ok 1 - day 08 part 1: 1594\n\
+             not ok 2 - day 08 part 2: a,b # expected 758\n\
+             1..2\n",
+            render(Format::Tap)
+        );
+    }
+}
@@ -1,19 +1,136 @@
+use super::error::{Error, Result};
 use std::fs::File;
-use std::io::{BufRead, BufReader, Error};
+use std::io::{BufRead, BufReader};
 use std::path::Path;
 
-pub fn read_strings_from_file(s: &str) -> Result<Vec<String>, Error> {
+pub fn read_strings_from_file(s: &str) -> Result<Vec<String>> {
     let path = Path::new(s);
-    let input = File::open(path)?;
-    let list: Vec<String> = BufReader::new(input).lines().map(|l| l.unwrap()).collect();
-    Ok(list)
+    let input = File::open(path).map_err(|e| Error::io(s, e))?;
+    BufReader::new(input)
+        .lines()
+        .collect::<std::io::Result<Vec<String>>>()
+        .map_err(|e| Error::io(s, e))
 }
 
-pub fn parse_as<T>(strings: &Vec<String>) -> Vec<T>
-    where T: std::str::FromStr, <T as std::str::FromStr>::Err : std::fmt::Debug {
-    let results: Vec<T> = strings
+/// Parses every line with `f`, attaching the line number to any error.
+pub fn parse_lines<T, F>(strings: &[String], f: F) -> Result<Vec<T>>
+where
+    F: Fn(&str) -> Result<T>,
+{
+    strings
         .iter()
-        .map(|s| T::from_str(s).unwrap())
-        .collect();
-    results
+        .enumerate()
+        .map(|(i, s)| f(s).map_err(|e| e.at_line(i + 1)))
+        .collect()
+}
+
+pub fn parse_as<T>(strings: &[String]) -> Result<Vec<T>>
+where
+    T: std::str::FromStr,
+    <T as std::str::FromStr>::Err: std::fmt::Display,
+{
+    parse_lines(strings, |s| {
+        T::from_str(s).map_err(|e| Error::parse(format!("cannot parse {:?}: {}", s, e)))
+    })
+}
+
+/// A run of non-blank lines, stripped of trailing whitespace (including `\r`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    /// 1-based line number of the first line in the group
+    pub first_line: usize,
+    pub lines: Vec<String>,
+}
+
+impl Group {
+    /// Lines paired with their 1-based line numbers, for error reporting.
+    pub fn numbered_lines(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.lines
+            .iter()
+            .enumerate()
+            .map(move |(i, line)| (self.first_line + i, &line[..]))
+    }
+}
+
+/// Iterator over the blank-line separated groups of its lines, see `groups`.
+pub struct Groups<I> {
+    lines: I,
+    line_no: usize,
+}
+
+impl<I, S> Iterator for Groups<I>
+where
+    I: Iterator<Item = S>,
+    S: AsRef<str>,
+{
+    type Item = Group;
+
+    fn next(&mut self) -> Option<Group> {
+        let mut group: Option<Group> = None;
+        for line in self.lines.by_ref() {
+            self.line_no += 1;
+            let line = line.as_ref().trim_end();
+            if line.trim_start().is_empty() {
+                if group.is_some() {
+                    break;
+                }
+            } else {
+                let first_line = self.line_no;
+                group
+                    .get_or_insert_with(|| Group {
+                        first_line,
+                        lines: Vec::new(),
+                    })
+                    .lines
+                    .push(line.to_string());
+            }
+        }
+        group
+    }
+}
+
+/// Splits lines into groups separated by one or more blank lines, lazily.
+pub fn groups<I, S>(lines: I) -> Groups<I::IntoIter>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    Groups {
+        lines: lines.into_iter(),
+        line_no: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_separated_by_blank_lines() {
+        let text = "\r\nab\r\nc  \r\n\r\n \t\r\n\r\nd\r\n";
+        let gs: Vec<Group> = groups(text.split('\n')).collect();
+        assert_eq!(
+            vec![
+                Group {
+                    first_line: 2,
+                    lines: vec!["ab".to_string(), "c".to_string()]
+                },
+                Group {
+                    first_line: 7,
+                    lines: vec!["d".to_string()]
+                },
+            ],
+            gs
+        );
+        assert_eq!(
+            vec![(2, "ab"), (3, "c")],
+            gs[0].numbered_lines().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn groups_of_nothing() {
+        assert_eq!(0, groups(Vec::<String>::new()).count());
+        assert_eq!(0, groups(vec!["", "  "]).count());
+    }
 }
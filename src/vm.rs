@@ -0,0 +1,433 @@
+//! The handheld game console from day 8: an assembler for its text format and
+//! a `Machine` that runs the result one instruction at a time.
+
+pub mod analysis;
+pub mod cfg;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod listing;
+pub mod trace;
+
+use super::error::{Error, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Opcode {
+    /// Adds the argument to the accumulator.
+    Acc,
+    /// Jumps relative to itself.
+    Jmp,
+    /// Does nothing.
+    Nop,
+}
+
+impl Opcode {
+    /// The opcode that day 8's corruption may have turned this one into.
+    pub fn flipped(self) -> Option<Opcode> {
+        match self {
+            Opcode::Acc => None,
+            Opcode::Jmp => Some(Opcode::Nop),
+            Opcode::Nop => Some(Opcode::Jmp),
+        }
+    }
+}
+
+impl FromStr for Opcode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "acc" => Ok(Opcode::Acc),
+            "jmp" => Ok(Opcode::Jmp),
+            "nop" => Ok(Opcode::Nop),
+            _ => Err(Error::parse(format!("unknown opcode {:?}", s))),
+        }
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Opcode::Acc => "acc",
+            Opcode::Jmp => "jmp",
+            Opcode::Nop => "nop",
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub argument: i32,
+}
+
+impl Instruction {
+    pub fn new(opcode: Opcode, argument: i32) -> Self {
+        Self { opcode, argument }
+    }
+
+    /// The registers after executing this instruction with `registers`.
+    pub fn execute(&self, registers: Registers) -> Registers {
+        match self.opcode {
+            Opcode::Acc => Registers {
+                accumulator: registers.accumulator + self.argument as i64,
+                address: registers.address + 1,
+            },
+            Opcode::Jmp => Registers {
+                address: registers.address + self.argument as i64,
+                ..registers
+            },
+            Opcode::Nop => Registers {
+                address: registers.address + 1,
+                ..registers
+            },
+        }
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_instruction(s, s, 0, &HashMap::new())
+    }
+}
+
+/// Parses `code`, a slice of `line`, as the instruction at `address`. Columns
+/// in errors count from the start of `line`.
+fn parse_instruction(
+    line: &str,
+    code: &str,
+    address: usize,
+    labels: &HashMap<&str, usize>,
+) -> Result<Instruction> {
+    let column = |token: &str| token.as_ptr() as usize - line.as_ptr() as usize + 1;
+    let mut parts = code.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(mnemonic), Some(argument), None) => {
+            let opcode = mnemonic
+                .parse::<Opcode>()
+                .map_err(|e| e.at_column(column(mnemonic)))?;
+            let argument = match argument.parse::<i32>() {
+                Ok(n) => n,
+                Err(_) if is_label(argument) => match labels.get(argument) {
+                    Some(&target) => target as i32 - address as i32,
+                    None => {
+                        return Err(Error::parse(format!("unknown label {:?}", argument))
+                            .at_column(column(argument)))
+                    }
+                },
+                Err(_) => {
+                    return Err(Error::parse(format!("invalid argument {:?}", argument))
+                        .at_column(column(argument)))
+                }
+            };
+            Ok(Instruction { opcode, argument })
+        }
+        _ => Err(Error::parse(format!(
+            "expected \"<operation> <argument>\", got {:?}",
+            code
+        ))),
+    }
+}
+
+fn is_label(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:+}", self.opcode, self.argument)
+    }
+}
+
+/// Turns the puzzle's text format into a program, one instruction per line.
+///
+/// On top of the puzzle's format, `;` starts a comment, blank lines are
+/// skipped and `name:` labels the next instruction, so that jumps can be
+/// written as `jmp name` and get compiled to the relative offset.
+pub fn assemble(strings: &[String]) -> Result<Vec<Instruction>> {
+    let mut labels = HashMap::new();
+    let mut code = Vec::new();
+    for (i, line) in strings.iter().enumerate() {
+        let mut text = line.split(';').next().unwrap().trim();
+        if let Some((label, rest)) = text.split_once(':') {
+            let label = label.trim();
+            if !is_label(label) {
+                return Err(Error::parse(format!("invalid label {:?}", label)).at_line(i + 1));
+            }
+            if labels.insert(label, code.len()).is_some() {
+                return Err(
+                    Error::parse(format!("label {:?} is defined twice", label)).at_line(i + 1)
+                );
+            }
+            text = rest.trim();
+        }
+        if !text.is_empty() {
+            code.push((i + 1, line, text));
+        }
+    }
+    code.iter()
+        .enumerate()
+        .map(|(address, &(n, line, text))| {
+            parse_instruction(line, text, address, &labels).map_err(|e| e.at_line(n))
+        })
+        .collect()
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Registers {
+    pub accumulator: i64,
+    /// Address of the next instruction, may point anywhere after a jump.
+    pub address: i64,
+}
+
+/// Why a machine stopped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Halt {
+    /// The address moved just past the last instruction.
+    Terminated,
+    /// The next instruction has already been executed once.
+    InfiniteLoop,
+    /// A jump went before the first instruction or further than one past the last.
+    JumpOutOfBounds,
+    /// `run_for` ran out of steps.
+    #[allow(dead_code)]
+    StepLimit,
+}
+
+impl fmt::Display for Halt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Halt::Terminated => "terminated",
+            Halt::InfiniteLoop => "infinite loop",
+            Halt::JumpOutOfBounds => "jump out of bounds",
+            Halt::StepLimit => "step limit reached",
+        })
+    }
+}
+
+/// Anything a machine can fetch instructions from: a plain program, or a view
+/// of one with a few edits applied.
+pub trait Program {
+    fn len(&self) -> usize;
+
+    fn fetch(&self, address: usize) -> Option<Instruction>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Program for [Instruction] {
+    fn len(&self) -> usize {
+        <[Instruction]>::len(self)
+    }
+
+    fn fetch(&self, address: usize) -> Option<Instruction> {
+        self.get(address).copied()
+    }
+}
+
+impl Program for Vec<Instruction> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn fetch(&self, address: usize) -> Option<Instruction> {
+        self.get(address).copied()
+    }
+}
+
+pub struct Machine<'a, P: Program + ?Sized = [Instruction]> {
+    program: &'a P,
+    registers: Registers,
+    executed: Vec<bool>,
+    steps: usize,
+}
+
+impl<'a, P: Program + ?Sized> Machine<'a, P> {
+    pub fn new(program: &'a P) -> Self {
+        Self {
+            program,
+            registers: Registers::default(),
+            executed: vec![false; program.len()],
+            steps: 0,
+        }
+    }
+
+    pub fn registers(&self) -> Registers {
+        self.registers
+    }
+
+    /// Instructions executed so far.
+    #[allow(dead_code)]
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Why the machine cannot execute its next instruction, if it can't.
+    pub fn halted(&self) -> Option<Halt> {
+        let address = self.registers.address;
+        if address == self.program.len() as i64 {
+            Some(Halt::Terminated)
+        } else if address < 0 || address > self.program.len() as i64 {
+            Some(Halt::JumpOutOfBounds)
+        } else if self.executed[address as usize] {
+            Some(Halt::InfiniteLoop)
+        } else {
+            None
+        }
+    }
+
+    /// Executes the next instruction, or reports why there is none to execute.
+    pub fn step(&mut self) -> std::result::Result<Instruction, Halt> {
+        if let Some(halt) = self.halted() {
+            return Err(halt);
+        }
+        let address = self.registers.address as usize;
+        let instruction = self
+            .program
+            .fetch(address)
+            .expect("address checked by halted()");
+        self.executed[address] = true;
+        self.steps += 1;
+        self.registers = instruction.execute(self.registers);
+        Ok(instruction)
+    }
+
+    /// Runs until the machine halts. Every instruction runs at most once, so
+    /// this always comes back.
+    pub fn run(&mut self) -> Halt {
+        loop {
+            if let Err(halt) = self.step() {
+                return halt;
+            }
+        }
+    }
+
+    /// Like `run`, but stops after `max_steps` more instructions.
+    #[allow(dead_code)]
+    pub fn run_for(&mut self, max_steps: usize) -> Halt {
+        for _ in 0..max_steps {
+            if let Err(halt) = self.step() {
+                return halt;
+            }
+        }
+        self.halted().unwrap_or(Halt::StepLimit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::{example, program, strings};
+    use super::*;
+
+    #[test]
+    fn assemble_and_display() {
+        let p = program(&["nop +0", "acc -99", "jmp +4"]);
+        assert_eq!(Instruction::new(Opcode::Acc, -99), p[1]);
+        let text: Vec<String> = p.iter().map(|i| i.to_string()).collect();
+        assert_eq!(vec!["nop +0", "acc -99", "jmp +4"], text);
+    }
+
+    #[test]
+    fn assembler_errors() {
+        let error = |lines: &[&str]| assemble(&strings(lines)).unwrap_err().to_string();
+        assert_eq!(
+            "line 3:1: unknown opcode \"hcf\"",
+            error(&["nop +0", "acc +1", "hcf +3"])
+        );
+        assert_eq!("line 1:5: invalid argument \"+x\"", error(&["jmp +x"]));
+        assert_eq!(
+            "line 1: expected \"<operation> <argument>\", got \"nop\"",
+            error(&["nop"])
+        );
+    }
+
+    #[test]
+    fn labels_and_comments() {
+        let p = program(&[
+            "start: nop +0  ; first",
+            "",
+            "  loop:",
+            "acc +1",
+            "jmp loop",
+            "jmp start",
+            "jmp end",
+            "end:",
+        ]);
+        let expected = program(&["nop +0", "acc +1", "jmp -1", "jmp -3", "jmp +1"]);
+        assert_eq!(expected, p);
+        let error = |lines: &[&str]| assemble(&strings(lines)).unwrap_err().to_string();
+        assert_eq!(
+            "line 1:5: unknown label \"nowhere\"",
+            error(&["jmp nowhere"])
+        );
+        assert_eq!(
+            "line 2: label \"a\" is defined twice",
+            error(&["a:", "a: nop +0"])
+        );
+        assert_eq!("line 1:4: unknown opcode \"hcf\"", error(&["x: hcf +1"]));
+    }
+
+    #[test]
+    fn halt_reasons() {
+        let mut machine = Machine::<[Instruction]>::new(&[]);
+        assert_eq!(Halt::Terminated, machine.run());
+
+        let p = example();
+        let mut machine = Machine::new(&p);
+        assert_eq!(Halt::InfiniteLoop, machine.run());
+        assert_eq!(
+            Registers {
+                accumulator: 5,
+                address: 1
+            },
+            machine.registers()
+        );
+        assert_eq!(7, machine.steps());
+
+        let p = program(&["acc +2", "jmp +2", "acc +1"]);
+        let mut machine = Machine::new(&p);
+        assert_eq!(Halt::Terminated, machine.run());
+        assert_eq!(
+            Registers {
+                accumulator: 2,
+                address: 3
+            },
+            machine.registers()
+        );
+
+        for &jump in &["jmp -2", "jmp +3"] {
+            let p = program(&["nop +0", jump, "nop +0"]);
+            assert_eq!(Halt::JumpOutOfBounds, Machine::new(&p).run());
+        }
+    }
+
+    #[test]
+    fn stepping_and_step_limit() {
+        let p = program(&["acc +1", "acc +1", "acc +1"]);
+        let mut machine = Machine::new(&p);
+        assert_eq!(Ok(Instruction::new(Opcode::Acc, 1)), machine.step());
+        assert_eq!(Halt::StepLimit, machine.run_for(1));
+        assert_eq!(
+            Registers {
+                accumulator: 2,
+                address: 2
+            },
+            machine.registers()
+        );
+        assert_eq!(Halt::Terminated, machine.run_for(1));
+        assert_eq!(Err(Halt::Terminated), machine.step());
+    }
+}
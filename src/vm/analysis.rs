@@ -0,0 +1,521 @@
+//! Abstract interpretation of console programs: how a run from every address
+//! ends, how flipping one `jmp`/`nop` changes the outcome of the program, and
+//! which accumulator values each address can see under any flips, all found
+//! without running anything.
+
+use super::cfg::ControlFlowGraph;
+use super::{Halt, Instruction, Opcode, Registers};
+use std::fmt::{self, Write};
+
+/// Updates to an address's interval before its growing bounds are widened to
+/// unbounded, so loops don't keep the analysis going forever.
+const WIDEN_AFTER: usize = 2;
+
+/// How a run ends: why it halts and the registers at that point, with the
+/// accumulator counted from where the run started.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Fate {
+    pub halt: Halt,
+    pub registers: Registers,
+}
+
+impl Fate {
+    fn new(halt: Halt, accumulator: i64, address: i64) -> Self {
+        Self {
+            halt,
+            registers: Registers {
+                accumulator,
+                address,
+            },
+        }
+    }
+
+    fn plus(self, accumulator: i64) -> Self {
+        Self::new(
+            self.halt,
+            self.registers.accumulator + accumulator,
+            self.registers.address,
+        )
+    }
+
+    pub fn terminates(&self) -> bool {
+        self.halt == Halt::Terminated
+    }
+}
+
+impl fmt::Display for Fate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Registers {
+            accumulator,
+            address,
+        } = self.registers;
+        match self.halt {
+            Halt::Terminated => write!(f, "terminates, acc {}", accumulator),
+            Halt::InfiniteLoop => write!(f, "loops at {}, acc {}", address, accumulator),
+            Halt::JumpOutOfBounds => write!(f, "jumps to {}, acc {}", address, accumulator),
+            Halt::StepLimit => write!(f, "{}, acc {}", self.halt, accumulator),
+        }
+    }
+}
+
+/// A closed range of accumulator values, `i64::MIN` and `i64::MAX` standing
+/// for unbounded ends.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Interval {
+    pub low: i64,
+    pub high: i64,
+}
+
+impl Interval {
+    pub fn exactly(value: i64) -> Self {
+        Self {
+            low: value,
+            high: value,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn contains(&self, value: i64) -> bool {
+        (self.low..=self.high).contains(&value)
+    }
+
+    fn join(self, other: Self) -> Self {
+        Self {
+            low: self.low.min(other.low),
+            high: self.high.max(other.high),
+        }
+    }
+
+    fn shift(self, delta: i64) -> Self {
+        let shift = |bound: i64| {
+            if bound == i64::MIN || bound == i64::MAX {
+                bound
+            } else {
+                bound.saturating_add(delta)
+            }
+        };
+        Self {
+            low: shift(self.low),
+            high: shift(self.high),
+        }
+    }
+
+    /// `next` with every bound that grew since `self` made unbounded.
+    fn widen(self, next: Self) -> Self {
+        Self {
+            low: if next.low < self.low {
+                i64::MIN
+            } else {
+                self.low
+            },
+            high: if next.high > self.high {
+                i64::MAX
+            } else {
+                self.high
+            },
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.low {
+            i64::MIN => write!(f, "[-inf, ")?,
+            low => write!(f, "[{}, ", low)?,
+        }
+        match self.high {
+            i64::MAX => write!(f, "+inf]"),
+            high => write!(f, "{}]", high),
+        }
+    }
+}
+
+fn accumulator_delta(instruction: &Instruction) -> i64 {
+    match instruction.opcode {
+        Opcode::Acc => instruction.argument as i64,
+        _ => 0,
+    }
+}
+
+/// Where `instruction` at `address` sends execution, in or out of bounds.
+fn target(instruction: &Instruction, address: usize) -> i64 {
+    instruction
+        .execute(Registers {
+            accumulator: 0,
+            address: address as i64,
+        })
+        .address
+}
+
+pub struct Analysis<'a> {
+    program: &'a [Instruction],
+    /// How a run starting at each address ends.
+    fates: Vec<Fate>,
+    /// The addresses a run from address 0 executes, in order, with the
+    /// accumulator before each.
+    path: Vec<(usize, i64)>,
+    /// Position of every address in `path`.
+    positions: Vec<Option<usize>>,
+    /// For every address whose run meets `path`, the position where it first
+    /// does and the accumulator gathered until then.
+    joins: Vec<Option<(usize, i64)>>,
+    /// Accumulator values on entry to every address and the end node, over
+    /// every set of flips. A flipped `jmp` is a `nop`, so every address can
+    /// be reached somehow.
+    ranges: Vec<Option<Interval>>,
+}
+
+impl<'a> Analysis<'a> {
+    pub fn new(program: &'a [Instruction]) -> Self {
+        let graph = ControlFlowGraph::new(program);
+        let fates = fates(program, &graph);
+        let mut path = Vec::new();
+        let mut positions = vec![None; program.len()];
+        let mut accumulator = 0;
+        for address in graph
+            .execution_path()
+            .into_iter()
+            .filter(|&a| a < program.len())
+        {
+            positions[address] = Some(path.len());
+            path.push((address, accumulator));
+            accumulator += accumulator_delta(&program[address]);
+        }
+        let joins = joins(program, &graph, &path, &positions);
+        let ranges = ranges(program);
+        Self {
+            program,
+            fates,
+            path,
+            positions,
+            joins,
+            ranges,
+        }
+    }
+
+    /// How a run starting at `address` with an empty accumulator ends; `address`
+    /// may be one past the end.
+    pub fn fate(&self, address: usize) -> Fate {
+        match self.fates.get(address) {
+            Some(&fate) => fate,
+            None => Fate::new(Halt::Terminated, 0, self.program.len() as i64),
+        }
+    }
+
+    /// How the program ends with the `jmp`/`nop` at `address` flipped, `None`
+    /// if there is no `jmp`/`nop` there.
+    pub fn flipped(&self, address: usize) -> Option<Fate> {
+        let instruction = self.program.get(address)?;
+        let flipped = Instruction::new(instruction.opcode.flipped()?, instruction.argument);
+        let i = match self.positions[address] {
+            Some(i) => i,
+            // never executed, so the flip changes nothing
+            None => return Some(self.fate(0)),
+        };
+        let before = self.path[i].1;
+        let next = target(&flipped, address);
+        if next < 0 || next > self.program.len() as i64 {
+            return Some(Fate::new(Halt::JumpOutOfBounds, before, next));
+        }
+        let (j, gathered) = match self.joins.get(next as usize).copied().flatten() {
+            Some(join) => join,
+            // off the original path for good, so ends just like a run from there
+            None => return Some(self.fate(next as usize).plus(before)),
+        };
+        let accumulator = before + gathered;
+        if j <= i {
+            return Some(Fate::new(
+                Halt::InfiniteLoop,
+                accumulator,
+                self.path[j].0 as i64,
+            ));
+        }
+        // back on the original path after the flip, and on it to its end
+        let original = self.fate(0);
+        let accumulator = accumulator + original.registers.accumulator - self.path[j].1;
+        if original.halt != Halt::InfiniteLoop {
+            return Some(Fate::new(
+                original.halt,
+                accumulator,
+                original.registers.address,
+            ));
+        }
+        let m =
+            self.positions[original.registers.address as usize].expect("loops back onto the path");
+        Some(if m <= i || m >= j {
+            Fate::new(Halt::InfiniteLoop, accumulator, self.path[m].0 as i64)
+        } else {
+            // round the original loop until the point the flip joined it
+            Fate::new(
+                Halt::InfiniteLoop,
+                accumulator + self.path[j].1 - self.path[m].1,
+                self.path[j].0 as i64,
+            )
+        })
+    }
+
+    /// Whether the program as it is executes `address`.
+    pub fn executed(&self, address: usize) -> bool {
+        self.positions.get(address).copied().flatten().is_some()
+    }
+
+    /// The accumulator values `address` can be entered with, over every set
+    /// of flips; `address` may be one past the end. Loops and joins make this
+    /// an over-approximation.
+    pub fn accumulator(&self, address: usize) -> Option<Interval> {
+        self.ranges.get(address).copied().flatten()
+    }
+
+    /// The single flips that make the program terminate, with how it ends.
+    pub fn repairs(&self) -> impl Iterator<Item = (usize, Fate)> + '_ {
+        self.path.iter().filter_map(move |&(address, _)| {
+            self.flipped(address)
+                .filter(Fate::terminates)
+                .map(|fate| (address, fate))
+        })
+    }
+}
+
+/// One line per instruction for the `analyze` command: how a run from there
+/// ends, how the program ends with it flipped, and the accumulator values it
+/// can be entered with. `*` marks the instructions the program executes, and
+/// the flips that make it terminate follow at the end.
+pub fn report(program: &[Instruction]) -> String {
+    let analysis = Analysis::new(program);
+    let mut report = String::new();
+    for (address, instruction) in program.iter().enumerate() {
+        let mark = if analysis.executed(address) { '*' } else { ' ' };
+        let flipped = analysis
+            .flipped(address)
+            .map_or("-".to_string(), |fate| fate.to_string());
+        let entered = analysis
+            .accumulator(address)
+            .map_or("never".to_string(), |range| range.to_string());
+        writeln!(
+            report,
+            "{:>5}{} {:<10} {:<24} flipped: {:<24} entered with {}",
+            address,
+            mark,
+            instruction.to_string(),
+            analysis.fate(address).to_string(),
+            flipped,
+            entered
+        )
+        .unwrap();
+    }
+    for (address, fate) in analysis.repairs() {
+        writeln!(report, "flipping {} {}", address, fate).unwrap();
+    }
+    report
+}
+
+/// Fates of runs from every address: each walk follows successors until an
+/// address with a known fate, the end, a jump out of bounds or a cycle, then
+/// hands the fate back along the way.
+fn fates(program: &[Instruction], graph: &ControlFlowGraph) -> Vec<Fate> {
+    let len = program.len();
+    let mut fates: Vec<Option<Fate>> = vec![None; len];
+    let mut walking = vec![false; len];
+    for start in 0..len {
+        let mut stack = Vec::new();
+        let mut node = start;
+        let mut fate = loop {
+            if node == len {
+                break Fate::new(Halt::Terminated, 0, len as i64);
+            }
+            if let Some(fate) = fates[node] {
+                break fate;
+            }
+            if walking[node] {
+                let position = stack.iter().rposition(|&a| a == node).unwrap();
+                let cycle = stack.split_off(position);
+                let sum = cycle.iter().map(|&a| accumulator_delta(&program[a])).sum();
+                for &a in &cycle {
+                    fates[a] = Some(Fate::new(Halt::InfiniteLoop, sum, a as i64));
+                    walking[a] = false;
+                }
+                break fates[node].unwrap();
+            }
+            match graph.successor(node) {
+                Some(next) => {
+                    walking[node] = true;
+                    stack.push(node);
+                    node = next;
+                }
+                None => {
+                    let fate = Fate::new(Halt::JumpOutOfBounds, 0, target(&program[node], node));
+                    fates[node] = Some(fate);
+                    break fate;
+                }
+            }
+        };
+        for &a in stack.iter().rev() {
+            fate = fate.plus(accumulator_delta(&program[a]));
+            fates[a] = Some(fate);
+            walking[a] = false;
+        }
+    }
+    fates.into_iter().map(Option::unwrap).collect()
+}
+
+/// Where runs from every address first meet `path`, found by walking the
+/// edges backwards from each address on it.
+fn joins(
+    program: &[Instruction],
+    graph: &ControlFlowGraph,
+    path: &[(usize, i64)],
+    positions: &[Option<usize>],
+) -> Vec<Option<(usize, i64)>> {
+    let predecessors = graph.predecessors();
+    let mut joins = vec![None; program.len()];
+    for (j, &(address, _)) in path.iter().enumerate() {
+        joins[address] = Some((j, 0));
+        let mut stack = vec![(address, 0)];
+        while let Some((node, gathered)) = stack.pop() {
+            for &p in predecessors[node]
+                .iter()
+                .filter(|&&p| positions[p].is_none())
+            {
+                let gathered = gathered + accumulator_delta(&program[p]);
+                joins[p] = Some((j, gathered));
+                stack.push((p, gathered));
+            }
+        }
+    }
+    joins
+}
+
+/// Accumulator intervals by a worklist fixpoint in which every `jmp`/`nop`
+/// may go either way.
+fn ranges(program: &[Instruction]) -> Vec<Option<Interval>> {
+    let len = program.len();
+    let mut ranges = vec![None; len + 1];
+    let mut updates = vec![0; len + 1];
+    ranges[0] = Some(Interval::exactly(0));
+    let mut worklist = vec![0];
+    while let Some(address) = worklist.pop() {
+        if address == len {
+            continue;
+        }
+        let instruction = program[address];
+        let out = ranges[address]
+            .unwrap()
+            .shift(accumulator_delta(&instruction));
+        let mut targets = vec![target(&instruction, address)];
+        if let Some(opcode) = instruction.opcode.flipped() {
+            targets.push(target(
+                &Instruction::new(opcode, instruction.argument),
+                address,
+            ));
+        }
+        for next in targets
+            .into_iter()
+            .filter(|&t| (0..=len as i64).contains(&t))
+        {
+            let next = next as usize;
+            let range = match ranges[next] {
+                None => out,
+                Some(range) => {
+                    let joined = range.join(out);
+                    if joined == range {
+                        continue;
+                    }
+                    updates[next] += 1;
+                    if updates[next] > WIDEN_AFTER {
+                        range.widen(joined)
+                    } else {
+                        joined
+                    }
+                }
+            };
+            ranges[next] = Some(range);
+            worklist.push(next);
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::{example, program};
+    use super::*;
+
+    #[test]
+    fn fates_of_the_example() {
+        let program = example();
+        let analysis = Analysis::new(&program);
+        assert_eq!(Fate::new(Halt::InfiniteLoop, 5, 1), analysis.fate(0));
+        assert_eq!(Fate::new(Halt::InfiniteLoop, 5, 4), analysis.fate(4));
+        assert_eq!(Fate::new(Halt::Terminated, 6, 9), analysis.fate(8));
+        assert_eq!(Fate::new(Halt::Terminated, 0, 9), analysis.fate(9));
+    }
+
+    #[test]
+    fn flips_of_the_example() {
+        let program = example();
+        let analysis = Analysis::new(&program);
+        assert_eq!(Some(Fate::new(Halt::Terminated, 8, 9)), analysis.flipped(7));
+        assert_eq!(
+            Some(Fate::new(Halt::InfiniteLoop, 0, 0)),
+            analysis.flipped(0)
+        );
+        assert_eq!(
+            Some(Fate::new(Halt::InfiniteLoop, 4, 1)),
+            analysis.flipped(2)
+        );
+        assert_eq!(
+            Some(Fate::new(Halt::InfiniteLoop, -94, 6)),
+            analysis.flipped(4)
+        );
+        assert_eq!(None, analysis.flipped(5));
+        assert_eq!(
+            vec![(7, Fate::new(Halt::Terminated, 8, 9))],
+            analysis.repairs().collect::<Vec<_>>()
+        );
+        assert!(!analysis.executed(5) && !analysis.executed(8));
+        assert!(analysis.accumulator(9).unwrap().contains(8));
+    }
+
+    #[test]
+    fn report_of_the_example() {
+        let report = report(&example());
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(10, lines.len());
+        assert_eq!(
+            "    7* jmp -4     loops at 7, acc 5        flipped: terminates, acc 8        entered with [-inf, +inf]",
+            lines[7]
+        );
+        assert_eq!("flipping 7 terminates, acc 8", lines[9]);
+    }
+
+    #[test]
+    fn intervals() {
+        let straight = program(&["acc +2", "nop +2", "acc -5"]);
+        let analysis = Analysis::new(&straight);
+        assert_eq!(Some(Interval { low: -3, high: 2 }), analysis.accumulator(3));
+        assert_eq!(Some(Interval::exactly(2)), analysis.accumulator(2));
+        assert_eq!(None, analysis.accumulator(4));
+        // a loop that may add forever gets widened
+        let looping = program(&["acc +1", "nop -1"]);
+        let analysis = Analysis::new(&looping);
+        assert_eq!("[0, +inf]", analysis.accumulator(0).unwrap().to_string());
+        assert_eq!("[1, +inf]", analysis.accumulator(2).unwrap().to_string());
+    }
+
+    #[test]
+    fn out_of_bounds_and_empty() {
+        let program = program(&["jmp +2", "acc +1", "jmp -3", "acc +7"]);
+        let analysis = Analysis::new(&program);
+        assert_eq!(Fate::new(Halt::JumpOutOfBounds, 0, -1), analysis.fate(0));
+        assert_eq!(Fate::new(Halt::JumpOutOfBounds, 1, -1), analysis.fate(1));
+        assert_eq!(Some(Fate::new(Halt::Terminated, 7, 4)), analysis.flipped(2));
+        assert_eq!(
+            Some(Fate::new(Halt::JumpOutOfBounds, 1, -1)),
+            analysis.flipped(0)
+        );
+        assert!(!analysis.executed(1));
+        let analysis = Analysis::new(&[]);
+        assert_eq!(Fate::new(Halt::Terminated, 0, 0), analysis.fate(0));
+        assert_eq!(0, analysis.repairs().count());
+    }
+}
@@ -0,0 +1,169 @@
+//! Control flow of console programs. Every instruction has exactly one
+//! successor, so the graph is a functional graph over the addresses plus one
+//! extra node, `len`, standing for normal termination.
+
+use super::{Instruction, Registers};
+
+pub struct ControlFlowGraph {
+    /// Successor of every address, `None` for jumps out of bounds.
+    successors: Vec<Option<usize>>,
+}
+
+/// Where `instruction` at `address` goes next in a program of `len` instructions.
+fn successor(instruction: &Instruction, address: usize, len: usize) -> Option<usize> {
+    let next = instruction
+        .execute(Registers {
+            accumulator: 0,
+            address: address as i64,
+        })
+        .address;
+    if (0..=len as i64).contains(&next) {
+        Some(next as usize)
+    } else {
+        None
+    }
+}
+
+impl ControlFlowGraph {
+    pub fn new(program: &[Instruction]) -> Self {
+        let successors = program
+            .iter()
+            .enumerate()
+            .map(|(address, instruction)| successor(instruction, address, program.len()))
+            .collect();
+        Self { successors }
+    }
+
+    /// The node standing for normal termination.
+    pub fn end(&self) -> usize {
+        self.successors.len()
+    }
+
+    pub fn successor(&self, address: usize) -> Option<usize> {
+        self.successors.get(address).copied().flatten()
+    }
+
+    pub fn predecessors(&self) -> Vec<Vec<usize>> {
+        let mut predecessors = vec![Vec::new(); self.end() + 1];
+        for (address, successor) in self.successors.iter().enumerate() {
+            if let Some(next) = successor {
+                predecessors[*next].push(address);
+            }
+        }
+        predecessors
+    }
+
+    /// Addresses (and the end node) from which execution terminates normally,
+    /// found by walking the edges backwards from the end node.
+    pub fn reaching_termination(&self) -> Vec<bool> {
+        let predecessors = self.predecessors();
+        let mut reaches = vec![false; self.end() + 1];
+        let mut stack = vec![self.end()];
+        reaches[self.end()] = true;
+        while let Some(node) = stack.pop() {
+            for &p in &predecessors[node] {
+                if !reaches[p] {
+                    reaches[p] = true;
+                    stack.push(p);
+                }
+            }
+        }
+        reaches
+    }
+
+    /// The addresses execution visits from address 0, in order, up to the
+    /// first repeated address, jump out of bounds or the end node.
+    pub fn execution_path(&self) -> Vec<usize> {
+        let mut visited = vec![false; self.end() + 1];
+        let mut path = Vec::new();
+        let mut node = Some(0);
+        while let Some(address) = node {
+            if visited[address] {
+                break;
+            }
+            visited[address] = true;
+            path.push(address);
+            node = self.successor(address);
+        }
+        path
+    }
+}
+
+/// A program made to terminate by flipping one `jmp`/`nop`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repair {
+    pub address: usize,
+    pub program: Vec<Instruction>,
+}
+
+/// Finds the flip that makes `program` terminate in linear time: the flipped
+/// instruction has to lie on the current execution path and send it into the
+/// set of addresses that already reach termination. `None` if the program
+/// terminates as it is or no single flip helps.
+pub fn repair(program: &[Instruction]) -> Option<Repair> {
+    let graph = ControlFlowGraph::new(program);
+    let reaches = graph.reaching_termination();
+    if reaches[0] {
+        return None;
+    }
+    graph
+        .execution_path()
+        .into_iter()
+        .filter(|&a| a < graph.end())
+        .find_map(|address| {
+            let instruction = program[address];
+            let flipped = Instruction::new(instruction.opcode.flipped()?, instruction.argument);
+            let next = successor(&flipped, address, program.len())?;
+            if reaches[next] {
+                let mut program = program.to_vec();
+                program[address] = flipped;
+                Some(Repair { address, program })
+            } else {
+                None
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::{example, program};
+    use super::super::{Halt, Machine, Opcode};
+    use super::*;
+
+    #[test]
+    fn graph_of_the_example() {
+        let graph = ControlFlowGraph::new(&example());
+        assert_eq!(Some(6), graph.successor(2));
+        assert_eq!(vec![0, 1, 2, 6, 7, 3, 4], graph.execution_path());
+        let reaches = graph.reaching_termination();
+        let reaching: Vec<usize> = (0..=graph.end()).filter(|&a| reaches[a]).collect();
+        assert_eq!(vec![8, 9], reaching);
+    }
+
+    #[test]
+    fn repair_the_example() {
+        let repaired = repair(&example()).unwrap();
+        assert_eq!(7, repaired.address);
+        assert_eq!(Instruction::new(Opcode::Nop, -4), repaired.program[7]);
+        let mut machine = Machine::new(&repaired.program);
+        assert_eq!(Halt::Terminated, machine.run());
+        assert_eq!(8, machine.registers().accumulator);
+    }
+
+    #[test]
+    fn nothing_to_repair() {
+        assert_eq!(None, repair(&program(&["acc +1", "jmp +2", "acc +1"])));
+        assert_eq!(None, repair(&program(&["jmp +0", "jmp -1"])));
+        assert_eq!(None, repair(&[]));
+    }
+
+    #[test]
+    fn out_of_bounds_jumps_never_terminate() {
+        let graph = ControlFlowGraph::new(&program(&["jmp +5", "jmp -2", "nop +0"]));
+        assert_eq!(None, graph.successor(0));
+        assert_eq!(None, graph.successor(1));
+        assert_eq!(vec![false, false, true, true], graph.reaching_termination());
+        let repaired = repair(&program(&["jmp +5", "nop +0"])).unwrap();
+        assert_eq!(0, repaired.address);
+    }
+}
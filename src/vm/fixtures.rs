@@ -0,0 +1,21 @@
+//! Programs shared by the tests of the console and of everything built on it.
+
+use super::{assemble, Instruction};
+
+/// The example program from the day 8 puzzle: it loops after accumulating 5,
+/// and flipping the `jmp` at 7 makes it terminate with 8.
+pub const EXAMPLE: [&str; 9] = [
+    "nop +0", "acc +1", "jmp +4", "acc +3", "jmp -3", "acc -99", "acc +1", "jmp -4", "acc +6",
+];
+
+pub fn strings(lines: &[&str]) -> Vec<String> {
+    lines.iter().map(|s| s.to_string()).collect()
+}
+
+pub fn program(lines: &[&str]) -> Vec<Instruction> {
+    assemble(&strings(lines)).unwrap()
+}
+
+pub fn example() -> Vec<Instruction> {
+    program(&EXAMPLE)
+}
@@ -0,0 +1,127 @@
+//! Disassembly into the labelled syntax `assemble` accepts, annotated with
+//! addresses, basic blocks and code that can never run.
+
+use super::cfg::ControlFlowGraph;
+use super::{Instruction, Opcode};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Where each `jmp` lands, if that is inside the program or just past it.
+fn jump_target(instruction: &Instruction, address: usize, len: usize) -> Option<usize> {
+    match instruction.opcode {
+        Opcode::Jmp => {
+            let target = address as i64 + instruction.argument as i64;
+            if (0..=len as i64).contains(&target) {
+                Some(target as usize)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn label(address: usize, len: usize) -> String {
+    if address == len {
+        "end".to_string()
+    } else {
+        format!("L{}", address)
+    }
+}
+
+/// A listing that assembles back into `program`: jumps refer to generated
+/// labels, a blank line starts every basic block, and every instruction is
+/// commented with its address and whether execution from address 0 reaches it.
+pub fn disassemble(program: &[Instruction]) -> String {
+    let len = program.len();
+    let targets: BTreeSet<usize> = program
+        .iter()
+        .enumerate()
+        .filter_map(|(address, instruction)| jump_target(instruction, address, len))
+        .collect();
+    let mut reached = vec![false; len + 1];
+    for address in ControlFlowGraph::new(program).execution_path() {
+        reached[address] = true;
+    }
+    let mut listing = String::new();
+    for (address, instruction) in program.iter().enumerate() {
+        let after_jump = address > 0 && program[address - 1].opcode == Opcode::Jmp;
+        if address > 0 && (after_jump || targets.contains(&address)) {
+            listing.push('\n');
+        }
+        if targets.contains(&address) {
+            writeln!(listing, "{}:", label(address, len)).unwrap();
+        }
+        let code = match jump_target(instruction, address, len) {
+            Some(target) => format!("{} {}", instruction.opcode, label(target, len)),
+            None => instruction.to_string(),
+        };
+        let note = if reached[address] { "" } else { " unreachable" };
+        writeln!(listing, "    {:<12}; {}{}", code, address, note).unwrap();
+    }
+    if targets.contains(&len) {
+        writeln!(listing, "\n{}:", label(len, len)).unwrap();
+    }
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::assemble;
+    use super::super::fixtures::{example, program};
+    use super::*;
+
+    fn round_trip(program: &[Instruction]) -> Vec<Instruction> {
+        let strings: Vec<String> = disassemble(program)
+            .lines()
+            .map(|s| s.to_string())
+            .collect();
+        assemble(&strings).unwrap()
+    }
+
+    #[test]
+    fn listing_of_the_example() {
+        let p = example();
+        assert_eq!(
+            "    nop +0      ; 0
+
+L1:
+    acc +1      ; 1
+    jmp L6      ; 2
+
+L3:
+    acc +3      ; 3
+    jmp L1      ; 4
+
+    acc -99     ; 5 unreachable
+
+L6:
+    acc +1      ; 6
+    jmp L3      ; 7
+
+    acc +6      ; 8 unreachable
+",
+            disassemble(&p)
+        );
+        assert_eq!(p, round_trip(&p));
+    }
+
+    #[test]
+    fn end_label_and_wild_jumps() {
+        let p = program(&["jmp +2", "jmp -7", "nop +5"]);
+        assert_eq!(
+            "    jmp L2      ; 0
+
+    jmp -7      ; 1 unreachable
+
+L2:
+    nop +5      ; 2
+",
+            disassemble(&p)
+        );
+        let p = program(&["acc +1", "jmp +2", "acc +2"]);
+        assert!(disassemble(&p).ends_with("    acc +2      ; 2 unreachable\n\nend:\n"));
+        assert_eq!(p, round_trip(&p));
+        assert_eq!(p, round_trip(&round_trip(&p)));
+    }
+}
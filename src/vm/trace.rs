@@ -0,0 +1,171 @@
+//! Recording what a machine executed, to see how a program ends up looping.
+
+use super::{Halt, Machine, Opcode, Program};
+use serde::Serialize;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraceEntry {
+    pub address: usize,
+    pub opcode: Opcode,
+    pub argument: i32,
+    pub accumulator_delta: i64,
+    /// The accumulator after the instruction.
+    pub accumulator: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Trace {
+    pub halt: Halt,
+    /// The address that would have run a second time, for `Halt::InfiniteLoop`.
+    pub loop_entry: Option<usize>,
+    pub entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    /// Runs `machine` to its halt, recording every instruction on the way.
+    pub fn record<P: Program + ?Sized>(machine: &mut Machine<P>) -> Self {
+        let mut entries = Vec::new();
+        loop {
+            let before = machine.registers();
+            match machine.step() {
+                Ok(instruction) => entries.push(TraceEntry {
+                    address: before.address as usize,
+                    opcode: instruction.opcode,
+                    argument: instruction.argument,
+                    accumulator_delta: machine.registers().accumulator - before.accumulator,
+                    accumulator: machine.registers().accumulator,
+                }),
+                Err(halt) => {
+                    let loop_entry = match halt {
+                        Halt::InfiniteLoop => Some(machine.registers().address as usize),
+                        _ => None,
+                    };
+                    return Self {
+                        halt,
+                        loop_entry,
+                        entries,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Index of the first entry of the loop body.
+    fn loop_start(&self) -> Option<usize> {
+        let entry = self.loop_entry?;
+        self.entries.iter().position(|e| e.address == entry)
+    }
+
+    /// The instructions that make up the loop, once round, starting at its entry.
+    pub fn loop_body(&self) -> Option<&[TraceEntry]> {
+        self.loop_start().map(|start| &self.entries[start..])
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("traces serialize")
+    }
+
+    /// One row per executed instruction, loop body rows marked with `*`.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "{:>6}{:>7}  {:<4}{:>6}{:>8}{:>8}\n",
+            "step", "addr", "op", "arg", "delta", "acc"
+        );
+        let loop_start = self.loop_start().unwrap_or(self.entries.len());
+        for (step, e) in self.entries.iter().enumerate() {
+            writeln!(
+                text,
+                "{:>6}{:>7}  {:<4}{:>+6}{:>+8}{:>8}{}",
+                step + 1,
+                e.address,
+                e.opcode.to_string(),
+                e.argument,
+                e.accumulator_delta,
+                e.accumulator,
+                if step >= loop_start { " *" } else { "" }
+            )
+            .unwrap();
+        }
+        match (self.loop_entry, self.loop_body()) {
+            (Some(entry), Some(body)) => writeln!(
+                text,
+                "infinite loop entered at address {}, {} instructions adding {:+} per round",
+                entry,
+                body.len(),
+                body.iter().map(|e| e.accumulator_delta).sum::<i64>()
+            ),
+            _ => writeln!(text, "{}", self.halt),
+        }
+        .unwrap();
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::{self, program};
+    use super::*;
+
+    fn trace(lines: &[&str]) -> Trace {
+        Trace::record(&mut Machine::new(&program(lines)))
+    }
+
+    fn example() -> Trace {
+        trace(&fixtures::EXAMPLE)
+    }
+
+    #[test]
+    fn loop_entry_and_body() {
+        let trace = example();
+        assert_eq!(Halt::InfiniteLoop, trace.halt);
+        assert_eq!(Some(1), trace.loop_entry);
+        let body: Vec<usize> = trace
+            .loop_body()
+            .unwrap()
+            .iter()
+            .map(|e| e.address)
+            .collect();
+        assert_eq!(vec![1, 2, 6, 7, 3, 4], body);
+        assert_eq!(
+            TraceEntry {
+                address: 3,
+                opcode: Opcode::Acc,
+                argument: 3,
+                accumulator_delta: 3,
+                accumulator: 5
+            },
+            trace.entries[5]
+        );
+    }
+
+    #[test]
+    fn text_listing() {
+        assert_eq!(
+            "  step   addr  op     arg   delta     acc
+     1      0  nop     +0      +0       0
+     2      1  acc     +1      +1       1 *
+     3      2  jmp     +4      +0       1 *
+     4      6  acc     +1      +1       2 *
+     5      7  jmp     -4      +0       2 *
+     6      3  acc     +3      +3       5 *
+     7      4  jmp     -3      +0       5 *
+infinite loop entered at address 1, 6 instructions adding +5 per round
+",
+            example().to_text()
+        );
+        let text = trace(&["acc +2"]).to_text();
+        assert!(text.ends_with("     1      0  acc     +2      +2       2\nterminated\n"));
+    }
+
+    #[test]
+    fn json_export() {
+        let json: serde_json::Value =
+            serde_json::from_str(&trace(&["acc +2", "jmp -1"]).to_json()).unwrap();
+        assert_eq!("infinite_loop", json["halt"]);
+        assert_eq!(0, json["loop_entry"]);
+        assert_eq!("acc", json["entries"][0]["opcode"]);
+        assert_eq!(2, json["entries"][0]["accumulator_delta"]);
+        assert_eq!(2, json["entries"].as_array().unwrap().len());
+    }
+}